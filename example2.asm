@@ -1,5 +1,5 @@
 .ORG $FFFC
-.BYTES $00 $06
+.WORD $0600
 
 .ORG $0010
 .BYTES $20
@@ -0,0 +1,306 @@
+// Operand expressions, parsed from the tokens of an operand and evaluated once the labels
+// they use are known.
+//
+// The binary operators have the usual C precedence, from loosest to tightest:
+// `|`, `^`, `&`, `<< >>`, `+ -`, `* / %`. The unary `-`, `<` (low byte) and `>` (high byte)
+// bind tighter than any of them, `<label+1` is the low byte of `label` plus one.
+
+use std::collections::HashMap;
+
+use crate::asm::lexer::{unescape, Token, TokenType};
+use crate::asm::{AsmError, Pos, Symbol};
+
+#[derive(Debug)]
+pub enum Radix {
+    Hex,
+    Dec,
+    Oct,
+    Bin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Negate,
+    Low,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(i32),
+    Label(Symbol),
+    // the address of the instruction or directive it is in, written `*`
+    Current,
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>, Symbol),
+}
+
+#[derive(Debug, Clone)]
+pub struct Value {
+    // written with more digits than a zero page address has
+    pub long: bool,
+    pub symbol: Symbol,
+    pub expr: Expr,
+}
+
+impl Value {
+    pub fn eval(&self, labels: &HashMap<String, u16>, current: u16) -> Result<i32, AsmError> {
+        self.expr.eval(labels, current)
+    }
+}
+
+impl Expr {
+    pub fn eval(&self, labels: &HashMap<String, u16>, current: u16) -> Result<i32, AsmError> {
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Label(label) => match labels.get(&label.text) {
+                Some(addr) => *addr as i32,
+                None => {
+                    return Err(AsmError::new(
+                        &format!("Undefined label: {}", label.text),
+                        Some(label.clone()),
+                    ))
+                }
+            },
+            Expr::Current => current as i32,
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(labels, current)?;
+                match op {
+                    UnaryOp::Negate => value.wrapping_neg(),
+                    UnaryOp::Low => value & 0xFF,
+                    UnaryOp::High => (value >> 8) & 0xFF,
+                }
+            }
+            Expr::Binary(op, left, right, symbol) => {
+                let left = left.eval(labels, current)?;
+                let right = right.eval(labels, current)?;
+                let result = match op {
+                    BinaryOp::Add => Some(left.wrapping_add(right)),
+                    BinaryOp::Sub => Some(left.wrapping_sub(right)),
+                    BinaryOp::Mul => Some(left.wrapping_mul(right)),
+                    BinaryOp::Div => left.checked_div(right),
+                    BinaryOp::Mod => left.checked_rem(right),
+                    BinaryOp::And => Some(left & right),
+                    BinaryOp::Or => Some(left | right),
+                    BinaryOp::Xor => Some(left ^ right),
+                    BinaryOp::ShiftLeft => {
+                        u32::try_from(right).ok().and_then(|r| left.checked_shl(r))
+                    }
+                    BinaryOp::ShiftRight => {
+                        u32::try_from(right).ok().and_then(|r| left.checked_shr(r))
+                    }
+                };
+                match result {
+                    Some(value) => value,
+                    None => {
+                        return Err(AsmError::new(
+                            &format!("Invalid operation {left} {} {right}", symbol.text),
+                            Some(symbol.clone()),
+                        ))
+                    }
+                }
+            }
+        })
+    }
+}
+
+pub fn parse_number(token: &Token, radix: Radix) -> Result<(i32, bool), AsmError> {
+    if token.token != TokenType::Number {
+        return Err(AsmError::new(
+            &format!("Expected number, found {}", token.symbol.text),
+            Some(token.symbol.clone()),
+        ));
+    };
+    let value = match i32::from_str_radix(
+        token.symbol.text.as_str(),
+        match radix {
+            Radix::Bin => 2,
+            Radix::Oct => 8,
+            Radix::Dec => 10,
+            Radix::Hex => 16,
+        },
+    ) {
+        Ok(res) => res,
+        Err(_) => {
+            return Err(AsmError::new(
+                format!("can't parse '{}' as number", token.symbol.text).as_str(),
+                Some(token.symbol.clone()),
+            ))
+        }
+    };
+    let is16bit = value > 255
+        || token.symbol.text.chars().count()
+            > match radix {
+                Radix::Bin => 8,
+                Radix::Oct => 4,
+                Radix::Dec => 3,
+                Radix::Hex => 2,
+            };
+    return Ok((value, is16bit));
+}
+
+// the symbol covering all the tokens, for error messages
+pub fn span(tokens: &[Token]) -> Option<Symbol> {
+    let first = tokens.first()?;
+    let last = tokens.last()?;
+    Some(Symbol {
+        start: first.symbol.start.clone(),
+        end: Pos {
+            file: first.symbol.start.file,
+            line: first.symbol.start.line,
+            col: if last.symbol.end.line == first.symbol.start.line {
+                last.symbol.end.col
+            } else {
+                first.symbol.end.col
+            },
+        },
+        text: tokens.iter().map(|t| t.symbol.text.as_str()).collect(),
+    })
+}
+
+// parses the whole token slice as one expression
+pub fn parse_expr(tokens: &[Token]) -> Result<Value, AsmError> {
+    let Some(symbol) = span(tokens) else {
+        return Err(AsmError::new("Missing value", None));
+    };
+    let mut parser = ExprParser {
+        tokens,
+        pos: 0,
+        long: false,
+    };
+    let expr = parser.binary(0)?;
+    if let Some(token) = tokens.get(parser.pos) {
+        return Err(AsmError::new(
+            &format!("Unexpected {} in expression", token.symbol.text),
+            Some(token.symbol.clone()),
+        ));
+    }
+    Ok(Value {
+        // only a lone number keeps the size it was written with
+        long: parser.long && matches!(expr, Expr::Number(_)),
+        symbol,
+        expr,
+    })
+}
+
+// the binary operators by precedence level, loosest first
+const LEVELS: [&[(TokenType, BinaryOp)]; 6] = [
+    &[(TokenType::Pipe, BinaryOp::Or)],
+    &[(TokenType::Caret, BinaryOp::Xor)],
+    &[(TokenType::Amp, BinaryOp::And)],
+    &[
+        (TokenType::ShiftLeft, BinaryOp::ShiftLeft),
+        (TokenType::ShiftRight, BinaryOp::ShiftRight),
+    ],
+    &[
+        (TokenType::Plus, BinaryOp::Add),
+        (TokenType::Minus, BinaryOp::Sub),
+    ],
+    &[
+        (TokenType::Star, BinaryOp::Mul),
+        (TokenType::Slash, BinaryOp::Div),
+        (TokenType::Percent, BinaryOp::Mod),
+    ],
+];
+
+struct ExprParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    long: bool,
+}
+
+impl ExprParser<'_> {
+    fn next(&mut self) -> Result<&Token, AsmError> {
+        let token = self.tokens.get(self.pos).ok_or_else(|| {
+            AsmError::new(
+                "Early end of expression",
+                self.tokens.last().map(|t| t.symbol.clone()),
+            )
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, AsmError> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some((token, op)) = self.tokens.get(self.pos).and_then(|token| {
+            LEVELS[level]
+                .iter()
+                .find(|(kind, _)| *kind == token.token)
+                .map(|(_, op)| (token, *op))
+        }) {
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right), token.symbol.clone());
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, AsmError> {
+        let token = self.next()?;
+        let op = match token.token {
+            TokenType::Minus => UnaryOp::Negate,
+            TokenType::Less => UnaryOp::Low,
+            TokenType::Greater => UnaryOp::High,
+            _ => {
+                self.pos -= 1;
+                return self.primary();
+            }
+        };
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, AsmError> {
+        let token = self.next()?.clone();
+        let radix = match token.token {
+            TokenType::Bin => Radix::Bin,
+            TokenType::Oct => Radix::Oct,
+            TokenType::Hex => Radix::Hex,
+            TokenType::Number => {
+                self.pos -= 1;
+                Radix::Dec
+            }
+            TokenType::Identifier => return Ok(Expr::Label(token.symbol)),
+            TokenType::Star => return Ok(Expr::Current),
+            TokenType::Char => {
+                // the lexer made sure it is a single byte
+                let value = unescape(&token.symbol.text).unwrap()[0];
+                return Ok(Expr::Number(value as i32));
+            }
+            TokenType::LParen => {
+                let expr = self.binary(0)?;
+                let close = self.next()?;
+                if close.token != TokenType::RParen {
+                    return Err(AsmError::new("Expected )", Some(close.symbol.clone())));
+                }
+                return Ok(expr);
+            }
+            _ => {
+                return Err(AsmError::new(
+                    &format!("Expected value, found {}", token.symbol.text),
+                    Some(token.symbol),
+                ))
+            }
+        };
+        let (value, long) = parse_number(self.next()?, radix)?;
+        self.long |= long;
+        Ok(Expr::Number(value))
+    }
+}
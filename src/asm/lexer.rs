@@ -1,4 +1,6 @@
-use crate::asm::{Pos, Symbol};
+use std::str::Chars;
+
+use crate::asm::{AsmError, Pos, Symbol};
 
 #[derive(Debug)]
 pub enum LState {
@@ -28,16 +30,98 @@ pub enum TokenType {
     Oct,
     NewLine,
     Dot,
-    Equals
+    Equals,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Amp,
+    Pipe,
+    Caret,
+    ShiftLeft,
+    ShiftRight,
+    Less,
+    Greater,
+    Char,
+    Comma,
+    String,
+}
+
+// the tokens an expression can end with, a `%` right after one is the modulo operator
+fn ends_value(token: &Token) -> bool {
+    matches!(
+        token.token,
+        TokenType::Number | TokenType::Identifier | TokenType::RParen | TokenType::Char
+    )
+}
+
+// the rest of a quoted literal, with the quotes and the escape sequences as written
+fn quoted(chars: &mut Chars, quote: char) -> Option<String> {
+    let mut text = String::from(quote);
+    loop {
+        let char = chars.next()?;
+        text.push(char);
+        if char == '\\' {
+            text.push(chars.next()?);
+        } else if char == quote {
+            return Some(text);
+        }
+    }
 }
 
-pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
+// the bytes of a quoted literal, non ASCII characters are stored as UTF-8
+pub fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut chars = text[1..text.len() - 1].chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            bytes.extend(char.to_string().bytes());
+            continue;
+        }
+        let byte = match chars.next() {
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('0') => 0,
+            Some(c @ ('\\' | '"' | '\'')) => c as u8,
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 2)
+                    .ok_or_else(|| format!("Invalid escape \\x{hex}"))?
+            }
+            c => return Err(format!("Invalid escape \\{}", c.unwrap_or(' '))),
+        };
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Result<Vec<Token>, AsmError> {
+    lex_file(input, 0)
+}
+
+// an error at a position of the `file`th source file
+fn error_at(file: usize, line: usize, col: usize, text: String, reason: &str) -> AsmError {
+    let mut symbol = Symbol::new(line, col, text);
+    symbol.start.file = file;
+    symbol.end.file = file;
+    AsmError::new(reason, Some(symbol))
+}
+
+// the tokens of the `file`th source file of the program, lines are numbered from 1
+pub fn lex_file<'a>(
+    input: impl Iterator<Item = &'a String>,
+    file: usize,
+) -> Result<Vec<Token>, AsmError> {
     let lines = input.enumerate().map(|(i, l)| (i + 1, l));
     let mut tokens: Vec<Token> = vec![Token {
         token: TokenType::NewLine,
         symbol: Symbol {
-            start: Pos { line: 0, col: 0 },
-            end: Pos { line: 0, col: 0 },
+            start: Pos::new(0, 0),
+            end: Pos::new(0, 0),
             text: String::from("\n"),
         },
     }];
@@ -76,13 +160,7 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                         });
                         char = chars.next();
                         col_i += 1;
-                        state = LState::Number(
-                            Pos {
-                                line: line_i,
-                                col: col_i,
-                            },
-                            String::from(""),
-                        );
+                        state = LState::Number(Pos::new(line_i, col_i), String::from(""));
                     }
                     Some('@') => {
                         tokens.push(Token {
@@ -91,13 +169,22 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                         });
                         char = chars.next();
                         col_i += 1;
-                        state = LState::Number(
-                            Pos {
-                                line: line_i,
-                                col: col_i,
-                            },
-                            String::from(""),
-                        );
+                        state = LState::Number(Pos::new(line_i, col_i), String::from(""));
+                    }
+                    Some('%')
+                        if !matches!(chars.clone().next(), Some('0' | '1'))
+                            || tokens.last().is_some_and(|last| {
+                                ends_value(last)
+                                    && last.symbol.end.line == line_i
+                                    && last.symbol.end.col == col_i
+                            }) =>
+                    {
+                        tokens.push(Token {
+                            token: TokenType::Percent,
+                            symbol: Symbol::new(line_i, col_i, String::from('%')),
+                        });
+                        char = chars.next();
+                        col_i += 1;
                     }
                     Some('%') => {
                         tokens.push(Token {
@@ -106,13 +193,7 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                         });
                         char = chars.next();
                         col_i += 1;
-                        state = LState::Number(
-                            Pos {
-                                line: line_i,
-                                col: col_i,
-                            },
-                            String::from(""),
-                        );
+                        state = LState::Number(Pos::new(line_i, col_i), String::from(""));
                     }
                     Some('(') => {
                         tokens.push(Token {
@@ -131,28 +212,33 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                         col_i += 1;
                     }
                     Some(',') => {
-                        match chars.next() {
-                            Some(txt @ 'x') | Some(txt @ 'X') => {
+                        // `,x` and `,y` are index registers unless a longer name follows
+                        let mut ahead = chars.clone();
+                        let register = ahead.next();
+                        let is_register = !ahead
+                            .next()
+                            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+                        match register {
+                            Some(txt @ ('x' | 'X' | 'y' | 'Y')) if is_register => {
                                 tokens.push(Token {
-                                    token: TokenType::CommaX,
+                                    token: if txt.eq_ignore_ascii_case(&'x') {
+                                        TokenType::CommaX
+                                    } else {
+                                        TokenType::CommaY
+                                    },
                                     symbol: Symbol::new(line_i, col_i, format!(",{txt}")),
                                 });
+                                chars.next();
                                 char = chars.next();
                                 col_i += 2;
                             }
-                            Some(txt @ 'y') | Some(txt @ 'Y') => {
+                            _ => {
                                 tokens.push(Token {
-                                    token: TokenType::CommaY,
-                                    symbol: Symbol::new(line_i, col_i, format!(",{txt}")),
+                                    token: TokenType::Comma,
+                                    symbol: Symbol::new(line_i, col_i, String::from(',')),
                                 });
                                 char = chars.next();
-                                col_i += 2;
-                            }
-                            t => {
-                                panic!(
-                                    "Invalid char at pos {line_i}:{col_i} '{}'",
-                                    t.map_or(String::from("EOF"), String::from)
-                                );
+                                col_i += 1;
                             }
                         };
                     }
@@ -172,29 +258,93 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                         char = chars.next();
                         col_i += 1;
                     }
+                    Some(op @ ('+' | '-' | '*' | '/' | '&' | '|' | '^')) => {
+                        tokens.push(Token {
+                            token: match op {
+                                '+' => TokenType::Plus,
+                                '-' => TokenType::Minus,
+                                '*' => TokenType::Star,
+                                '/' => TokenType::Slash,
+                                '&' => TokenType::Amp,
+                                '|' => TokenType::Pipe,
+                                _ => TokenType::Caret,
+                            },
+                            symbol: Symbol::new(line_i, col_i, String::from(op)),
+                        });
+                        char = chars.next();
+                        col_i += 1;
+                    }
+                    Some(op @ ('<' | '>')) => {
+                        char = chars.next();
+                        if char == Some(op) {
+                            tokens.push(Token {
+                                token: if op == '<' {
+                                    TokenType::ShiftLeft
+                                } else {
+                                    TokenType::ShiftRight
+                                },
+                                symbol: Symbol::new(line_i, col_i, format!("{op}{op}")),
+                            });
+                            char = chars.next();
+                            col_i += 2;
+                        } else {
+                            tokens.push(Token {
+                                token: if op == '<' {
+                                    TokenType::Less
+                                } else {
+                                    TokenType::Greater
+                                },
+                                symbol: Symbol::new(line_i, col_i, String::from(op)),
+                            });
+                            col_i += 1;
+                        }
+                    }
+                    Some(quote @ ('\'' | '"')) => {
+                        // a character literal, its value is the character code, or a string
+                        let Some(text) = quoted(&mut chars, quote) else {
+                            let text = String::from(quote);
+                            return Err(error_at(
+                                file,
+                                line_i,
+                                col_i,
+                                text,
+                                "Unterminated literal",
+                            ));
+                        };
+                        match unescape(&text) {
+                            Ok(bytes) if quote == '"' || bytes.len() == 1 => {}
+                            Ok(_) => {
+                                let reason = "Invalid char literal";
+                                return Err(error_at(file, line_i, col_i, text, reason));
+                            }
+                            Err(reason) => {
+                                return Err(error_at(file, line_i, col_i, text, &reason));
+                            }
+                        }
+                        let len = text.chars().count();
+                        tokens.push(Token {
+                            token: if quote == '"' {
+                                TokenType::String
+                            } else {
+                                TokenType::Char
+                            },
+                            symbol: Symbol::new(line_i, col_i, text),
+                        });
+                        char = chars.next();
+                        col_i += len;
+                    }
                     Some(curr_char) if curr_char.is_whitespace() => {
                         char = chars.next();
                         col_i += 1;
                     }
                     Some(curr_char @ '0'..='9') => {
-                        state = LState::Number(
-                            Pos {
-                                line: line_i,
-                                col: col_i,
-                            },
-                            String::from(curr_char),
-                        );
+                        state = LState::Number(Pos::new(line_i, col_i), String::from(curr_char));
                         char = chars.next();
                         col_i += 1;
                     }
                     Some(curr_char @ ('a'..='z' | 'A'..='Z' | '_')) => {
-                        state = LState::Identifier(
-                            Pos {
-                                line: line_i,
-                                col: col_i,
-                            },
-                            String::from(curr_char),
-                        );
+                        state =
+                            LState::Identifier(Pos::new(line_i, col_i), String::from(curr_char));
                         char = chars.next();
                         col_i += 1;
                     }
@@ -202,7 +352,9 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                         break;
                     }
                     Some(curr_char) => {
-                        panic!("Invalid char at pos {line_i}:{col_i} '{}'", curr_char);
+                        let reason = format!("Invalid char '{curr_char}'");
+                        let text = String::from(curr_char);
+                        return Err(error_at(file, line_i, col_i, text, &reason));
                     }
                 },
                 LState::Identifier(_, ref mut text)
@@ -217,10 +369,7 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                         token: TokenType::Identifier,
                         symbol: Symbol {
                             start,
-                            end: Pos {
-                                line: line_i,
-                                col: col_i,
-                            },
+                            end: Pos::new(line_i, col_i),
                             text: text.to_string(),
                         },
                     });
@@ -235,43 +384,38 @@ pub fn lex<'a>(input: impl Iterator<Item = &'a String>) -> Vec<Token> {
                 }
                 LState::Number(start, text) => {
                     if text.is_empty() {
-                        panic!(
-                            "Invalid char at pos {line_i}:{col_i}, expected number, found {}",
-                            char.map_or(String::from("NULL"), String::from)
+                        let found = char.map_or(String::new(), String::from);
+                        let reason = format!(
+                            "Expected number, found {}",
+                            char.map_or(String::from("end of line"), String::from)
                         );
+                        return Err(error_at(file, line_i, col_i, found, &reason));
                     }
                     tokens.push(Token {
                         token: TokenType::Number,
                         symbol: Symbol {
                             start,
-                            end: Pos {
-                                line: line_i,
-                                col: col_i,
-                            },
+                            end: Pos::new(line_i, col_i),
                             text: text.to_string(),
                         },
                     });
                     state = LState::Default;
-                } // _ => {
-                  //     panic!("Not Implemented");
-                  // }
+                }
             }
         }
 
         tokens.push(Token {
             token: TokenType::NewLine,
             symbol: Symbol {
-                start: Pos {
-                    line: line_i,
-                    col: col_i,
-                },
-                end: Pos {
-                    line: line_i + 1,
-                    col: col_i,
-                },
+                start: Pos::new(line_i, col_i),
+                end: Pos::new(line_i + 1, col_i),
                 text: String::from("\n"),
             },
         });
     }
-    return tokens;
+    for token in tokens.iter_mut() {
+        token.symbol.start.file = file;
+        token.symbol.end.file = file;
+    }
+    Ok(tokens)
 }
@@ -1,21 +1,32 @@
-use std::fs::File;
+use std::fs::{self, File};
 
+use std::collections::BTreeMap;
 use std::io;
 use std::io::BufRead;
-use std::path::Path;
-use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-use crate::asm::lexer::lex;
-use crate::asm::parser::parse;
+use crate::asm::parser::parse_with;
+use crate::asm::source::Sources;
+use crate::instruct::Variant;
+pub mod expr;
 pub mod lexer;
 pub mod parser;
+pub mod source;
 
 #[derive(Debug, Clone)]
 pub struct Pos {
+    // the index of the source file, 0 is the file given to the assembler
+    pub file: usize,
     pub line: usize,
     pub col: usize,
 }
 
+impl Pos {
+    pub fn new(line: usize, col: usize) -> Pos {
+        Pos { file: 0, line, col }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Symbol {
     pub start: Pos,
@@ -26,11 +37,8 @@ pub struct Symbol {
 impl Symbol {
     pub fn new(line: usize, col: usize, text: String) -> Symbol {
         Symbol {
-            start: Pos { line, col },
-            end: Pos {
-                line,
-                col: col + text.chars().count(),
-            },
+            start: Pos::new(line, col),
+            end: Pos::new(line, col + text.chars().count()),
             text,
         }
     }
@@ -60,30 +68,62 @@ where
 }
 
 pub fn assemble(input: Vec<String>) -> BTreeMap<u16, u8> {
-    let tokens = lex(input.iter());
+    assemble_for(input, Variant::Nmos6502)
+}
 
-    // println!("{:?}", tokens);
-    let res = parse(tokens);
-    match res {
-        Ok(value) => {
-            return value;
-        }
-        Err(error) => {
-            println!("ERROR: {}", error.reason);
-            if let Some(symbol) = error.symbol {
-                println!(" -> STDIN:{}:{}", symbol.start.line, symbol.start.col + 1);
-                println!("{} | {}", symbol.start.line, input[symbol.start.line - 1]);
-                let n_width = format!("{}", symbol.start.line).len();
-                let start = symbol.start.col;
-                let width = symbol.end.col - symbol.start.col;
-                println!(
-                    "{} | {}{}",
-                    " ".repeat(n_width),
-                    " ".repeat(start - 1),
-                    "~".repeat(width)
-                );
-            }
-            panic!("");
-        }
+// assembles with the opcode tables of the given processor, panics with the error report
+pub fn assemble_for(input: Vec<String>, variant: Variant) -> BTreeMap<u16, u8> {
+    try_assemble_source(input, None, variant, &[]).unwrap_or_else(|report| panic!("{report}"))
+}
+
+// assembles the file at `path`, the files it includes are looked up next to it and then in
+// `include_paths`
+pub fn assemble_file(
+    path: &Path,
+    variant: Variant,
+    include_paths: &[PathBuf],
+) -> Result<BTreeMap<u16, u8>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("ERROR: {}: {e}\n", path.display()))?;
+    let lines = text.lines().map(|l| l.to_string()).collect();
+    try_assemble_source(lines, Some(path), variant, include_paths)
+}
+
+// assembles the lines of the file at `path`, or of the standard input without one, the error
+// is returned as a message with the line it points at
+pub fn try_assemble_source(
+    input: Vec<String>,
+    path: Option<&Path>,
+    variant: Variant,
+    include_paths: &[PathBuf],
+) -> Result<BTreeMap<u16, u8>, String> {
+    let mut sources = Sources::new(include_paths.to_vec());
+    let name = path.map_or(String::from("STDIN"), |p| p.display().to_string());
+    let file = sources.add(name, path.map(Path::to_path_buf), input);
+    sources
+        .tokens(file)
+        .and_then(|tokens| parse_with(tokens, variant, &mut sources))
+        .map_err(|error| report(&error, &sources))
+}
+
+fn report(error: &AsmError, sources: &Sources) -> String {
+    let mut out = format!("ERROR: {}\n", error.reason);
+    if let Some(symbol) = &error.symbol {
+        let start = &symbol.start;
+        out += &format!(
+            " -> {}:{}:{}\n",
+            sources.name(start.file),
+            start.line,
+            start.col + 1
+        );
+        out += &format!("{} | {}\n", start.line, sources.line(start).unwrap_or(""));
+        let n_width = format!("{}", start.line).len();
+        let width = symbol.end.col.saturating_sub(start.col);
+        out += &format!(
+            "{} | {}{}\n",
+            " ".repeat(n_width),
+            " ".repeat(start.col.saturating_sub(1)),
+            "~".repeat(width)
+        );
     }
+    out
 }
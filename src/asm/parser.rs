@@ -1,63 +1,90 @@
-use crate::asm::lexer::{Token, TokenType};
+use crate::asm::expr::{parse_expr, parse_number, span, Expr, Radix, Value};
+use crate::asm::lexer::{unescape, Token, TokenType};
+use crate::asm::source::Sources;
 use crate::asm::{AsmError, Symbol};
-use crate::instruct::{AddressType, Instruct};
-use std::collections::{HashMap, BTreeMap};
-
-#[derive(Debug)]
-enum InterAddr {
-    Addr(AddressType, Option<Value>),
-    Label(Symbol),
-}
+use crate::instruct::{AddressType, Instruct, Variant};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug)]
 struct InterOpCode {
     symbol: Symbol,
     instruct: Instruct,
-    ins_addr: u16,
-    addr: InterAddr,
+    // the addressing mode depends on the labels, so it is picked again on every pass
+    operand: Vec<Token>,
 }
 
+// a value of a data directive
 #[derive(Debug)]
-pub enum Radix {
-    Hex,
-    Dec,
-    Oct,
-    Bin,
+enum Data {
+    Value(Value),
+    Text(Vec<u8>),
 }
 
+// a line of the program, before the addresses are known
 #[derive(Debug)]
-struct Value {
-    long: bool,
-    symbol: Symbol,
-    value: i32,
+enum Item {
+    Label(Symbol),
+    Org(Value),
+    Bytes(Vec<u8>),
+    // values of `width` bytes each, little endian
+    Data(usize, Vec<Data>),
+    // `count` bytes, left as they are without a fill value
+    Fill(Value, Option<Value>),
+    // up to the next multiple of the value
+    Align(Value, Option<Value>),
+    Instruction(InterOpCode),
 }
 
+// where an item ended up on the last pass, with the addressing mode of an instruction
+struct Placed {
+    ins_addr: u16,
+    size: usize,
+    operand: Option<(AddressType, Option<Value>)>,
+}
+
+// passes before the label addresses are given up on
+const MAX_PASSES: usize = 16;
+
 enum PState {
     Default,
     PostIntruction(Symbol, Instruct),
-    PostNumber(Symbol, Instruct, Value),
     PostDirective(Symbol, Directive),
 }
 
+#[allow(clippy::upper_case_acronyms)]
 enum Directive {
     ORG,
-    BYTES
+    BYTES,
+    BYTE,
+    WORD,
+    DWORD,
+    TEXT,
+    ASCIIZ,
+    FILL,
+    RES,
+    ALIGN,
+    INCBIN,
 }
 
 impl Directive {
     pub fn from_str(val: &str) -> Option<Directive> {
-        match val {
+        match val.to_uppercase().as_str() {
             "ORG" => Some(Directive::ORG),
             "BYTES" => Some(Directive::BYTES),
-            _ => None
+            "BYTE" => Some(Directive::BYTE),
+            "WORD" => Some(Directive::WORD),
+            "DWORD" => Some(Directive::DWORD),
+            "TEXT" => Some(Directive::TEXT),
+            "ASCIIZ" => Some(Directive::ASCIIZ),
+            "FILL" => Some(Directive::FILL),
+            "RES" => Some(Directive::RES),
+            "ALIGN" => Some(Directive::ALIGN),
+            "INCBIN" => Some(Directive::INCBIN),
+            _ => None,
         }
     }
 }
 
-fn is_keyword(text: &str) -> bool {
-    return &text.to_lowercase() == "define" || Instruct::from_str(text).is_some();
-}
-
 fn throw_newline(token: Option<Token>) -> Result<Token, AsmError> {
     return match token {
         t @ (None
@@ -69,50 +96,383 @@ fn throw_newline(token: Option<Token>) -> Result<Token, AsmError> {
     };
 }
 
-fn parse_number(token: Token, radix: Radix) -> Result<Value, AsmError> {
-    if token.token != TokenType::Number {
+// the tokens up to the end of the line, the newline is consumed
+fn rest_of_line(tokens: &mut impl Iterator<Item = Token>) -> Vec<Token> {
+    tokens
+        .take_while(|t| t.token != TokenType::NewLine)
+        .collect()
+}
+
+// the arguments of a directive, split at the commas outside of parentheses
+fn split_args(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut args = vec![vec![]];
+    let mut depth = 0;
+    for token in tokens {
+        match token.token {
+            TokenType::LParen => depth += 1,
+            TokenType::RParen => depth -= 1,
+            TokenType::Comma if depth == 0 => {
+                args.push(vec![]);
+                continue;
+            }
+            // `.byte 1,x` is a label called x
+            TokenType::CommaX | TokenType::CommaY if depth == 0 => {
+                let start = &token.symbol.start;
+                let name = token.symbol.text[1..].to_string();
+                let mut symbol = Symbol::new(start.line, start.col + 1, name);
+                symbol.start.file = start.file;
+                symbol.end.file = start.file;
+                args.push(vec![Token {
+                    token: TokenType::Identifier,
+                    symbol,
+                }]);
+                continue;
+            }
+            _ => {}
+        }
+        args.last_mut().unwrap().push(token);
+    }
+    args
+}
+
+// the values of a data directive, strings are only allowed for bytes
+fn parse_data(tokens: Vec<Token>, width: usize) -> Result<Vec<Data>, AsmError> {
+    split_args(tokens)
+        .into_iter()
+        .map(|arg| match arg.as_slice() {
+            [Token {
+                token: TokenType::String,
+                symbol,
+            }] => {
+                if width != 1 {
+                    return Err(AsmError::new(
+                        "Strings only go in .byte, .text and .asciiz",
+                        Some(symbol.clone()),
+                    ));
+                }
+                Ok(Data::Text(unescape(&symbol.text).unwrap()))
+            }
+            _ => Ok(Data::Value(parse_expr(&arg)?)),
+        })
+        .collect()
+}
+
+// a count and an optional fill value
+fn parse_fill(tokens: Vec<Token>) -> Result<(Value, Option<Value>), AsmError> {
+    let args = split_args(tokens);
+    match args.as_slice() {
+        [count] => Ok((parse_expr(count)?, None)),
+        [count, fill] => Ok((parse_expr(count)?, Some(parse_expr(fill)?))),
+        _ => Err(AsmError::new(
+            "Expected a count and an optional fill value",
+            args.get(2).and_then(|arg| span(arg)),
+        )),
+    }
+}
+
+// a size or alignment, it can only use the labels before it
+fn eval_count(
+    value: &Value,
+    labels: &HashMap<String, u16>,
+    ins_addr: u16,
+) -> Result<usize, AsmError> {
+    match value.eval(labels, ins_addr)? {
+        count @ 0..=0x10000 => Ok(count as usize),
+        count => Err(AsmError::new(
+            &format!("Invalid count {count}"),
+            Some(value.symbol.clone()),
+        )),
+    }
+}
+
+// the bytes of a binary file, from an optional offset and for an optional length
+fn include_binary(
+    tokens: Vec<Token>,
+    dir_symbol: Symbol,
+    sources: &Sources,
+) -> Result<Vec<u8>, AsmError> {
+    let args = split_args(tokens);
+    let (name, range) = match args
+        .split_first()
+        .map(|(name, range)| (name.as_slice(), range))
+    {
+        Some((
+            [Token {
+                token: TokenType::String,
+                symbol,
+            }],
+            range,
+        )) if range.len() <= 2 => (symbol, range),
+        _ => {
+            return Err(AsmError::new(
+                "Expected a file name, an optional offset and an optional length",
+                args.get(3).and_then(|arg| span(arg)).or(Some(dir_symbol)),
+            ))
+        }
+    };
+    let data = sources.read_binary(name)?;
+    // the values can't use labels, the size of the data has to be known right away
+    let constant = |arg: &Vec<Token>| -> Result<usize, AsmError> {
+        eval_count(&parse_expr(arg)?, &HashMap::new(), 0)
+    };
+    let offset = range.first().map(constant).transpose()?.unwrap_or(0);
+    let length = range.get(1).map(constant).transpose()?;
+    if offset > data.len() {
+        return Err(AsmError::new(
+            &format!(
+                "Offset {offset} is past the end of the {} byte file",
+                data.len()
+            ),
+            span(&range[0]),
+        ));
+    }
+    let end = length.map_or(data.len(), |length| offset + length);
+    if end > data.len() {
+        return Err(AsmError::new(
+            &format!(
+                "{} bytes from offset {offset} is past the end of the {} byte file",
+                end - offset,
+                data.len()
+            ),
+            span(&range[1]),
+        ));
+    }
+    Ok(data[offset..end].to_vec())
+}
+
+// the value in `width` little endian bytes
+fn data_bytes(
+    value: &Value,
+    width: usize,
+    labels: &HashMap<String, u16>,
+    ins_addr: u16,
+) -> Result<Vec<u8>, AsmError> {
+    let number = value.eval(labels, ins_addr)?;
+    let bits = 8 * width as u32;
+    // negative values are written as their two's complement
+    if bits < 32 && (number < -(1 << (bits - 1)) || number >= 1 << bits) {
         return Err(AsmError::new(
-            &format!("Expected number, found {}", token.symbol.text),
-            Some(token.symbol),
+            &format!("Value {number} doesnt fit in {width} bytes"),
+            Some(value.symbol.clone()),
         ));
+    }
+    Ok(number.to_le_bytes()[..width].to_vec())
+}
+
+// the labels of the previous pass and the address of the instruction, the labels it
+// didn't know yet are assumed to be absolute addresses
+struct Known<'a> {
+    labels: &'a HashMap<String, u16>,
+    ins_addr: u16,
+}
+
+impl Known<'_> {
+    fn is_short(&self, value: &Value) -> bool {
+        !value.long
+            && value
+                .eval(self.labels, self.ins_addr)
+                .is_ok_and(|v| (0..=0xFF).contains(&v))
+    }
+
+    // the zero page form if the value fits and the instruction has it, unless the size was
+    // given with `z:` or `a:`
+    fn zero_page_or(
+        &self,
+        variant: Variant,
+        ins: &Instruct,
+        value: &Value,
+        forced: Option<bool>,
+        zero_page: AddressType,
+        absolute: AddressType,
+    ) -> (AddressType, u16) {
+        let short = forced
+            .unwrap_or_else(|| self.is_short(value) && variant.op_code(ins, &zero_page).is_some());
+        if short {
+            (zero_page, 2)
+        } else {
+            (absolute, 3)
+        }
+    }
+}
+
+// the index of the parenthesis closing the one at the start
+fn closing_paren(operand: &[Token]) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in operand.iter().enumerate() {
+        match token.token {
+            TokenType::LParen => depth += 1,
+            TokenType::RParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// picks the addressing mode from the operand tokens, returns it with the instruction size.
+// An operand in parentheses is indirect, `(2+3)*4` is an expression but `(2+3)` is not.
+// `z:` and `a:` in front of a zero page or absolute operand choose the size
+fn parse_operand(
+    variant: Variant,
+    ins: &Instruct,
+    operand: &[Token],
+    known: &Known,
+) -> Result<(AddressType, Option<Value>, u16), AsmError> {
+    let (forced, operand) = match operand {
+        [Token {
+            token: TokenType::Identifier,
+            symbol,
+        }, Token {
+            token: TokenType::Colon,
+            ..
+        }, rest @ ..]
+            if matches!(symbol.text.as_str(), "z" | "Z" | "a" | "A") =>
+        {
+            (Some(symbol.text.eq_ignore_ascii_case("z")), rest)
+        }
+        _ => (None, operand),
     };
-    let value = match i32::from_str_radix(
-        token.symbol.text.as_str(),
-        match radix {
-            Radix::Bin => 2,
-            Radix::Oct => 8,
-            Radix::Dec => 10,
-            Radix::Hex => 16,
-        },
-    ) {
-        Ok(res) => res,
-        Err(_) => {
+    let is_rel = variant.op_code(ins, &AddressType::Relative).is_some();
+    let (mode, size, value) = match operand {
+        [] if forced.is_none() => return Ok((AddressType::Impl, None, 1)),
+        // `asl a`, the accumulator can't be used as a label
+        [Token {
+            token: TokenType::Identifier,
+            symbol,
+        }] if symbol.text.eq_ignore_ascii_case("a")
+            && variant.op_code(ins, &AddressType::Accumulator).is_some() =>
+        {
+            return Ok((AddressType::Accumulator, None, 1))
+        }
+        [Token {
+            token: TokenType::Hash,
+            ..
+        }, value @ ..] => (AddressType::Immediate, 2, parse_expr(value)?),
+        [Token {
+            token: TokenType::LParen,
+            ..
+        }, ..]
+            if closing_paren(operand).is_some_and(|close| {
+                close == operand.len() - 1
+                    || (close == operand.len() - 2 && operand[close + 1].token == TokenType::CommaY)
+            }) =>
+        {
+            let close = closing_paren(operand).unwrap();
+            let inner = &operand[1..close];
+            let indexed_y = close != operand.len() - 1;
+            match inner {
+                [value @ .., Token {
+                    token: TokenType::CommaX,
+                    symbol,
+                }] => {
+                    if indexed_y {
+                        return Err(AsmError::new("Unexpected Token", Some(symbol.clone())));
+                    }
+                    let value = parse_expr(value)?;
+                    // only JMP has the absolute form, it's on the 65C02
+                    if !known.is_short(&value) || *ins == Instruct::JMP {
+                        (AddressType::AbsoluteIndirectX, 3, value)
+                    } else {
+                        (AddressType::IndirectX, 2, value)
+                    }
+                }
+                _ if indexed_y => (AddressType::IndirectY, 2, parse_expr(inner)?),
+                _ => {
+                    let value = parse_expr(inner)?;
+                    if known.is_short(&value)
+                        && variant
+                            .op_code(ins, &AddressType::ZeroPageIndirect)
+                            .is_some()
+                    {
+                        (AddressType::ZeroPageIndirect, 2, value)
+                    } else {
+                        (AddressType::Indirect, 3, value)
+                    }
+                }
+            }
+        }
+        // `($10),x` reads like an indirect mode, but the 6502 has none indexed that way
+        [Token {
+            token: TokenType::LParen,
+            ..
+        }, .., Token {
+            token: TokenType::CommaX,
+            symbol,
+        }] if closing_paren(operand) == Some(operand.len() - 2) => {
             return Err(AsmError::new(
-                format!("can't parse '{}' as number", token.symbol.text).as_str(),
-                Some(token.symbol),
-            ))
+                "Invalid addressing mode, use ($nn,x) or ($nn),y",
+                Some(symbol.clone()),
+            ));
+        }
+        [value @ .., Token {
+            token: TokenType::CommaX,
+            ..
+        }] => {
+            let value = parse_expr(value)?;
+            let (mode, size) = known.zero_page_or(
+                variant,
+                ins,
+                &value,
+                forced,
+                AddressType::ZeroPageX,
+                AddressType::AbsoluteX,
+            );
+            (mode, size, value)
+        }
+        [value @ .., Token {
+            token: TokenType::CommaY,
+            ..
+        }] => {
+            let value = parse_expr(value)?;
+            let (mode, size) = known.zero_page_or(
+                variant,
+                ins,
+                &value,
+                forced,
+                AddressType::ZeroPageY,
+                AddressType::AbsoluteY,
+            );
+            (mode, size, value)
+        }
+        value if is_rel => (AddressType::Relative, 2, parse_expr(value)?),
+        value => {
+            let value = parse_expr(value)?;
+            let (mode, size) = known.zero_page_or(
+                variant,
+                ins,
+                &value,
+                forced,
+                AddressType::ZeroPage,
+                AddressType::Absolute,
+            );
+            (mode, size, value)
         }
     };
-    let is16bit = value > 255
-        || token.symbol.text.chars().count()
-            > match radix {
-                Radix::Bin => 8,
-                Radix::Oct => 4,
-                Radix::Dec => 3,
-                Radix::Hex => 2,
-            };
-    return Ok(Value {
-        long: is16bit,
-        symbol: token.symbol,
-        value,
-    });
+    let direct = matches!(
+        mode,
+        AddressType::ZeroPage
+            | AddressType::ZeroPageX
+            | AddressType::ZeroPageY
+            | AddressType::Absolute
+            | AddressType::AbsoluteX
+            | AddressType::AbsoluteY
+    );
+    if forced.is_some() && !direct {
+        return Err(AsmError::new(
+            "Size prefix on an operand that has no zero page form",
+            Some(value.symbol),
+        ));
+    }
+    Ok((mode, Some(value), size))
 }
 
-fn b_ext(tree: &mut BTreeMap<u16, u8>, start: u16, values: &[u8]) -> () {
-    let mut i = start;
-    for val in values {
-        tree.insert(i, *val);
-        i += 1;
+fn b_ext(tree: &mut BTreeMap<u16, u8>, start: u16, values: &[u8]) {
+    for (i, val) in values.iter().enumerate() {
+        tree.insert(start.wrapping_add(i as u16), *val);
     }
 }
 
@@ -155,8 +515,6 @@ pub fn extend_tokens(tokens: Vec<Token>) -> Result<Vec<Token>, AsmError> {
                 }
             }
             TokenType::Identifier => {
-                // println!("{token:?}");
-                // println!("{defines:?}");
                 if let Some((_, ref mut def_vec)) = parsing_define {
                     if let Some(define_tokens) = defines.get(&token.symbol.text) {
                         def_vec.append(&mut define_tokens.clone());
@@ -183,16 +541,23 @@ pub fn extend_tokens(tokens: Vec<Token>) -> Result<Vec<Token>, AsmError> {
     return Ok(new_tokens);
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<BTreeMap<u16, u8>, AsmError> {
+pub fn parse(tokens: Vec<Token>, variant: Variant) -> Result<BTreeMap<u16, u8>, AsmError> {
+    parse_with(tokens, variant, &mut Sources::default())
+}
+
+// parses a program whose `.include` and `.incbin` files are looked up in `sources`, the
+// files it includes are added to them
+pub fn parse_with(
+    tokens: Vec<Token>,
+    variant: Variant,
+    sources: &mut Sources,
+) -> Result<BTreeMap<u16, u8>, AsmError> {
+    let tokens = sources.expand(tokens)?;
     let mut tokens = extend_tokens(tokens)?.into_iter().peekable();
-    let mut labels: HashMap<String, u16> = HashMap::new();
     let mut state = PState::Default;
 
-    let mut instructions: Vec<InterOpCode> = vec![];
-
-    let mut result: BTreeMap<u16, u8> = BTreeMap::new();
+    let mut items: Vec<Item> = vec![];
 
-    let mut ins_addr = 0x0600;
     loop {
         match state {
             PState::Default => {
@@ -201,12 +566,18 @@ pub fn parse(tokens: Vec<Token>) -> Result<BTreeMap<u16, u8>, AsmError> {
                     match token.token {
                         TokenType::Identifier => {
                             if let Some(ins) = Instruct::from_str(token.symbol.text.as_str()) {
+                                if !variant.has(&ins) {
+                                    return Err(AsmError::new(
+                                        &format!("Instruction not available on the {variant}"),
+                                        Some(token.symbol),
+                                    ));
+                                }
                                 let ins_symbol = token.symbol;
                                 state = PState::PostIntruction(ins_symbol, ins);
                             } else if token.symbol.text.to_lowercase() == "define" {
                                 return Err(AsmError::new("Invalid token", Some(token.symbol)));
                             } else if tokens.next_if(|t| t.token == TokenType::Colon).is_some() {
-                                labels.insert(token.symbol.text, ins_addr);
+                                items.push(Item::Label(token.symbol));
                             } else {
                                 return Err(AsmError::new(
                                     "Unknown instruction or invalid token",
@@ -242,454 +613,342 @@ pub fn parse(tokens: Vec<Token>) -> Result<BTreeMap<u16, u8>, AsmError> {
                 }
             }
             PState::PostIntruction(ins_symbol, ins) => {
-                let curr_token = tokens.peek();
-                match curr_token {
-                    Some(Token {
-                        token: TokenType::Hash,
-                        ..
-                    }) => {
-                        tokens.next().unwrap();
-                        let mut token = throw_newline(tokens.next())?;
-                        let radix = match token.token {
-                            TokenType::Bin => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Bin
-                            }
-                            TokenType::Oct => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Oct
-                            }
-                            TokenType::Hex => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Hex
-                            }
-                            TokenType::Number => Radix::Dec,
-                            _ => unreachable!(),
-                        };
+                let operand = rest_of_line(&mut tokens);
+                // the operand is checked now, before its size matters
+                let known = Known {
+                    labels: &HashMap::new(),
+                    ins_addr: 0,
+                };
+                parse_operand(variant, &ins, &operand, &known)?;
+                items.push(Item::Instruction(InterOpCode {
+                    symbol: ins_symbol,
+                    instruct: ins,
+                    operand,
+                }));
+                state = PState::Default;
+            }
+            PState::PostDirective(dir_symbol, dir) => match dir {
+                Directive::BYTE | Directive::TEXT => {
+                    items.push(Item::Data(1, parse_data(rest_of_line(&mut tokens), 1)?));
+                    state = PState::Default;
+                }
+                Directive::ASCIIZ => {
+                    let mut data = parse_data(rest_of_line(&mut tokens), 1)?;
+                    data.push(Data::Text(vec![0]));
+                    items.push(Item::Data(1, data));
+                    state = PState::Default;
+                }
+                Directive::WORD | Directive::DWORD => {
+                    let width = if matches!(dir, Directive::WORD) { 2 } else { 4 };
+                    items.push(Item::Data(
+                        width,
+                        parse_data(rest_of_line(&mut tokens), width)?,
+                    ));
+                    state = PState::Default;
+                }
+                Directive::FILL => {
+                    let (count, fill) = parse_fill(rest_of_line(&mut tokens))?;
+                    let zero = Value {
+                        long: false,
+                        symbol: dir_symbol,
+                        expr: Expr::Number(0),
+                    };
+                    items.push(Item::Fill(count, Some(fill.unwrap_or(zero))));
+                    state = PState::Default;
+                }
+                Directive::RES => {
+                    let (count, fill) = parse_fill(rest_of_line(&mut tokens))?;
+                    items.push(Item::Fill(count, fill));
+                    state = PState::Default;
+                }
+                Directive::ALIGN => {
+                    let (boundary, fill) = parse_fill(rest_of_line(&mut tokens))?;
+                    items.push(Item::Align(boundary, fill));
+                    state = PState::Default;
+                }
+                Directive::INCBIN => {
+                    let data = include_binary(rest_of_line(&mut tokens), dir_symbol, sources)?;
+                    items.push(Item::Bytes(data));
+                    state = PState::Default;
+                }
+                Directive::ORG => {
+                    tokens.next_if(|t| t.token == TokenType::Equals);
+                    let operand = rest_of_line(&mut tokens);
+                    items.push(Item::Org(parse_expr(&operand)?));
+                    state = PState::Default;
+                }
+                Directive::BYTES => {
+                    let mut bytes = vec![];
+                    while let Some(curr_token) = tokens.next() {
+                        match curr_token.token {
+                            TokenType::Number
+                            | TokenType::Bin
+                            | TokenType::Oct
+                            | TokenType::Hex => {
+                                let mut token = curr_token;
+                                let radix = match token.token {
+                                    TokenType::Bin => {
+                                        token = throw_newline(tokens.next())?;
+                                        Radix::Bin
+                                    }
+                                    TokenType::Oct => {
+                                        token = throw_newline(tokens.next())?;
+                                        Radix::Oct
+                                    }
+                                    TokenType::Hex => {
+                                        token = throw_newline(tokens.next())?;
+                                        Radix::Hex
+                                    }
+                                    TokenType::Number => Radix::Dec,
+                                    _ => unreachable!(),
+                                };
+                                let (value, long) = parse_number(&token, radix)?;
+                                if long {
+                                    return Err(AsmError::new(
+                                        format!("{} is not u8", token.symbol.text).as_str(),
+                                        Some(token.symbol),
+                                    ));
+                                }
 
-                        let value = parse_number(token, radix)?;
-                        if value.long {
-                            return Err(AsmError::new(
-                                "number can't be bigger than 8 bits",
-                                Some(value.symbol),
-                            ));
-                        }
-                        instructions.push(InterOpCode {
-                            symbol: ins_symbol,
-                            instruct: ins,
-                            ins_addr,
-                            addr: InterAddr::Addr(AddressType::Immediate, Some(value)),
-                        });
-                        ins_addr += 2;
-                        state = PState::Default;
-                    }
-                    Some(Token {
-                        token: TokenType::LParen,
-                        ..
-                    }) => {
-                        tokens.next().unwrap();
-                        let mut token = throw_newline(tokens.next())?;
-                        let radix = match token.token {
-                            TokenType::Bin => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Bin
-                            }
-                            TokenType::Oct => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Oct
+                                bytes.push(value as u8);
                             }
-                            TokenType::Hex => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Hex
-                            }
-                            TokenType::Number => Radix::Dec,
-                            _ => unreachable!(),
-                        };
-
-                        let value = parse_number(token, radix)?;
-                        token = throw_newline(tokens.next())?;
-                        match token.token {
-                            TokenType::RParen => {
+                            TokenType::NewLine => {
                                 if let Some(Token {
-                                    token: TokenType::CommaY,
+                                    token:
+                                        TokenType::Number
+                                        | TokenType::Bin
+                                        | TokenType::Oct
+                                        | TokenType::Hex,
                                     ..
                                 }) = tokens.peek()
                                 {
-                                    tokens.next();
-                                    instructions.push(InterOpCode {
-                                        symbol: ins_symbol,
-                                        instruct: ins,
-                                        ins_addr,
-                                        addr: InterAddr::Addr(AddressType::IndirectY, Some(value)),
-                                    });
-                                    ins_addr += 2;
+                                    continue;
                                 } else {
-                                    instructions.push(InterOpCode {
-                                        symbol: ins_symbol,
-                                        instruct: ins,
-                                        ins_addr,
-                                        addr: InterAddr::Addr(AddressType::Indirect, Some(value)),
-                                    });
-                                    ins_addr += 3;
-                                }
-                            }
-                            TokenType::CommaX => {
-                                token = throw_newline(tokens.next())?;
-                                if let TokenType::RParen = token.token {
-                                    instructions.push(InterOpCode {
-                                        symbol: ins_symbol,
-                                        instruct: ins,
-                                        ins_addr,
-                                        addr: InterAddr::Addr(AddressType::IndirectX, Some(value)),
-                                    });
-                                    ins_addr += 2;
-                                } else {
-                                    return Err(AsmError::new(
-                                        "Unexpected Token",
-                                        Some(token.symbol),
-                                    ));
+                                    break;
                                 }
                             }
-                            _ => return Err(AsmError::new("Unexpected Token", Some(token.symbol))),
-                        }
-                        state = PState::Default;
-                    }
-                    Some(Token {
-                        token: TokenType::Bin | TokenType::Hex | TokenType::Oct,
-                        ..
-                    }) => {
-                        let mut token = throw_newline(tokens.next())?;
-                        let radix = match token.token {
-                            TokenType::Bin => Radix::Bin,
-                            TokenType::Oct => Radix::Oct,
-                            TokenType::Hex => Radix::Hex,
-                            _ => unreachable!(),
-                        };
-
-                        token = throw_newline(tokens.next())?;
-                        state = PState::PostNumber(ins_symbol, ins, parse_number(token, radix)?);
-                    }
-                    Some(Token {
-                        token: TokenType::Number,
-                        ..
-                    }) => {
-                        let token = throw_newline(tokens.next())?;
-                        state =
-                            PState::PostNumber(ins_symbol, ins, parse_number(token, Radix::Dec)?);
-                    }
-                    Some(
-                        token @ Token {
-                            token: TokenType::Identifier,
-                            ..
-                        },
-                    ) => {
-                        if is_keyword(token.symbol.text.as_str()) {
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Addr(AddressType::Impl, None),
-                            });
-                            ins_addr += 1;
-                        } else {
-                            let token = throw_newline(tokens.next())?;
-                            let is_rel = ins.get_op_code(&AddressType::Relative).is_some();
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Label(token.symbol),
-                            });
-                            if !is_rel {
-                                ins_addr += 1;
+                            _ => {
+                                return Err(AsmError::new(
+                                    format!("{} is not a valid number", curr_token.symbol.text)
+                                        .as_str(),
+                                    Some(curr_token.symbol),
+                                ))
                             }
-                            ins_addr += 2;
-                        }
-                        state = PState::Default;
-                    }
-                    None
-                    | Some(Token {
-                        token: TokenType::NewLine,
-                        ..
-                    }) => {
-                        tokens.next();
-                        instructions.push(InterOpCode {
-                            symbol: ins_symbol,
-                            instruct: ins,
-                            ins_addr,
-                            addr: InterAddr::Addr(AddressType::Impl, None),
-                        });
-                        ins_addr += 1;
-                        state = PState::Default;
-                    }
-                    _ => {
-                        unimplemented!();
-                    }
-                }
-            }
-            PState::PostNumber(ins_symbol, ins, value) => {
-                let curr_token = tokens.peek();
-                match curr_token {
-                    Some(Token {
-                        token: TokenType::CommaX,
-                        ..
-                    }) => {
-                        if value.long {
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Addr(AddressType::AbsoluteX, Some(value)),
-                            });
-                            ins_addr += 3;
-                        } else {
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Addr(AddressType::ZeroPageX, Some(value)),
-                            });
-                            ins_addr += 2;
-                        }
-                        tokens.next();
-                    }
-                    Some(Token {
-                        token: TokenType::CommaY,
-                        ..
-                    }) => {
-                        if value.long {
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Addr(AddressType::AbsoluteY, Some(value)),
-                            });
-                            ins_addr += 3;
-                        } else {
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Addr(AddressType::ZeroPageY, Some(value)),
-                            });
-                            ins_addr += 2;
-                        }
-                        tokens.next();
-                    }
-                    _ => {
-                        if value.long {
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Addr(AddressType::Absolute, Some(value)),
-                            });
-                            ins_addr += 3;
-                        } else {
-                            instructions.push(InterOpCode {
-                                symbol: ins_symbol,
-                                instruct: ins,
-                                ins_addr,
-                                addr: InterAddr::Addr(AddressType::ZeroPage, Some(value)),
-                            });
-                            ins_addr += 2;
                         }
                     }
+                    items.push(Item::Bytes(bytes));
+                    state = PState::Default;
                 }
-                state = PState::Default;
             },
-            PState::PostDirective(_dir_symbol, dir) => {
-                match dir {
-                    Directive::ORG => {
-                        tokens.next_if(|t| t.token == TokenType::Equals);
-                        let mut token = throw_newline(tokens.next())?;
-                        let radix = match token.token {
-                            TokenType::Bin => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Bin
-                            }
-                            TokenType::Oct => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Oct
-                            }
-                            TokenType::Hex => {
-                                token = throw_newline(tokens.next())?;
-                                Radix::Hex
-                            }
-                            TokenType::Number => Radix::Dec,
-                            _ => unreachable!(),
-                        };
-
-                        let value = parse_number(token, radix)?;
-
-                        ins_addr = value.value as u16;
-                        state = PState::Default;
-                    },
-                    Directive::BYTES => {
-                        while let Some(curr_token) = tokens.next() {
-                            match curr_token.token {
-                                TokenType::Number | TokenType::Bin | TokenType::Oct | TokenType::Hex => {
-                                    let mut token = curr_token;
-                                    let radix = match token.token {
-                                        TokenType::Bin => {
-                                            token = throw_newline(tokens.next())?;
-                                            Radix::Bin
-                                        }
-                                        TokenType::Oct => {
-                                            token = throw_newline(tokens.next())?;
-                                            Radix::Oct
-                                        }
-                                        TokenType::Hex => {
-                                            token = throw_newline(tokens.next())?;
-                                            Radix::Hex
-                                        }
-                                        TokenType::Number => Radix::Dec,
-                                        _ => unreachable!(),
-                                    };
-                                    let value = parse_number(token, radix)?;
-                                    if value.long {
-                                        return Err(AsmError::new(
-                                            format!("{} is not u8", value.symbol.text).as_str(),
-                                            Some(value.symbol)
-                                        ))
-                                    }
+        }
+    }
 
-                                    result.insert(ins_addr, value.value as u8);
-                                    ins_addr += 1;
-                                },
-                                TokenType::NewLine => {
-                                    if let Some(Token {token: TokenType::Number | TokenType::Bin | TokenType::Oct | TokenType::Hex, ..}) = tokens.peek() {
-                                        continue;
-                                    } else {
-                                        break;
-                                    }
-                                }
-                                _ => {
-                                    return Err(AsmError::new(
-                                        format!("{} is not a valid number", curr_token.symbol.text).as_str(),
-                                        Some(curr_token.symbol)
-                                    ))
-                                }
-                            }
-                        }
-                        state = PState::Default;
-                    }
-                }
-            }
+    // the sizes depend on the label addresses and the other way around, the passes are
+    // repeated until the labels stop moving
+    let mut labels: HashMap<String, u16> = HashMap::new();
+    for _ in 0..MAX_PASSES {
+        let (next, placed) = lay_out(&items, variant, &labels)?;
+        if next == labels {
+            return emit(&items, placed, variant, &labels);
         }
+        labels = next;
     }
+    let (next, _) = lay_out(&items, variant, &labels)?;
+    let moving = items.iter().find_map(|item| match item {
+        Item::Label(label) if next.get(&label.text) != labels.get(&label.text) => {
+            Some(label.clone())
+        }
+        _ => None,
+    });
+    Err(AsmError::new(
+        "Phase error, the label addresses never settle",
+        moving,
+    ))
+}
 
-    // print_instructions(&instructions);
-    for op in instructions.into_iter() {
-        match op.addr {
-            InterAddr::Label(label) => {
-                let label_addr = match labels.get(&label.text) {
-                    Some(x) => Ok(*x),
-                    None => Err(AsmError::new(&format!("Undefined label: {label:?}"), None)),
-                }?;
-                if let Some(op_code) = op.instruct.get_op_code(&AddressType::Absolute) {
-                    if label_addr > u16::MAX {
+// one pass, gives every label an address and every instruction a size using the labels
+// of the previous pass
+fn lay_out(
+    items: &[Item],
+    variant: Variant,
+    previous: &HashMap<String, u16>,
+) -> Result<(HashMap<String, u16>, Vec<Placed>), AsmError> {
+    let mut labels: HashMap<String, u16> = HashMap::new();
+    let mut placed = vec![];
+    let mut ins_addr: u16 = 0x0600;
+    for item in items {
+        let mut operand = None;
+        let start = ins_addr;
+        match item {
+            Item::Label(label) => {
+                if labels.insert(label.text.clone(), ins_addr).is_some() {
+                    return Err(AsmError::new(
+                        &format!("Label {} defined twice", label.text),
+                        Some(label.clone()),
+                    ));
+                }
+            }
+            // only the labels before it can move the origin
+            Item::Org(value) => {
+                ins_addr = match value.eval(&labels, ins_addr)? {
+                    addr @ 0..=0xFFFF => addr as u16,
+                    addr => {
                         return Err(AsmError::new(
-                            &format!("Absolute address doesnt fit in u16: {label_addr}"),
-                            Some(label),
-                        ));
+                            &format!("Address doesnt fit in u16: {addr}"),
+                            Some(value.symbol.clone()),
+                        ))
                     }
-                    let full_addr = label_addr;
-                    let low: u8 = ((full_addr & 0xFF00) >> 8) as u8;
-                    let high: u8 = (full_addr & 0x00FF) as u8;
-                    b_ext(&mut result, op.ins_addr, &[op_code, high, low]);
-                } else if let Some(op_code) = op.instruct.get_op_code(&AddressType::Relative) {
-                    let diff = (label_addr as i32) - (op.ins_addr as i32) - 2;
-                    let addr = match i8::try_from(diff) {
-                        Ok(val) => val as u8,
-                        Err(_) => {
-                            return Err(AsmError::new(
-                                &format!("Relative address doesnt fit in i8: {diff}"),
-                                Some(label),
-                            ))
+                };
+            }
+            Item::Bytes(bytes) => ins_addr = ins_addr.wrapping_add(bytes.len() as u16),
+            Item::Data(width, data) => {
+                let size: usize = data
+                    .iter()
+                    .map(|data| match data {
+                        Data::Value(_) => *width,
+                        Data::Text(text) => text.len(),
+                    })
+                    .sum();
+                ins_addr = ins_addr.wrapping_add(size as u16);
+            }
+            Item::Fill(count, _) => {
+                let count = eval_count(count, &labels, ins_addr)?;
+                ins_addr = ins_addr.wrapping_add(count as u16);
+            }
+            Item::Align(boundary, _) => {
+                let boundary = eval_count(boundary, &labels, ins_addr)?.max(1);
+                let padding = (boundary - ins_addr as usize % boundary) % boundary;
+                ins_addr = ins_addr.wrapping_add(padding as u16);
+            }
+            Item::Instruction(op) => {
+                let known = Known {
+                    labels: previous,
+                    ins_addr,
+                };
+                let (addr, value, size) =
+                    parse_operand(variant, &op.instruct, &op.operand, &known)?;
+                operand = Some((addr, value));
+                ins_addr = ins_addr.wrapping_add(size);
+            }
+        }
+        placed.push(Placed {
+            ins_addr: start,
+            size: ins_addr.wrapping_sub(start) as usize,
+            operand,
+        });
+    }
+    Ok((labels, placed))
+}
+
+// writes the bytes once the labels are settled
+fn emit(
+    items: &[Item],
+    placed: Vec<Placed>,
+    variant: Variant,
+    labels: &HashMap<String, u16>,
+) -> Result<BTreeMap<u16, u8>, AsmError> {
+    let mut result: BTreeMap<u16, u8> = BTreeMap::new();
+    for (item, placed) in items.iter().zip(placed) {
+        let ins_addr = placed.ins_addr;
+        let (op, (addr, value)) = match (item, placed.operand) {
+            (Item::Bytes(bytes), _) => {
+                b_ext(&mut result, ins_addr, bytes);
+                continue;
+            }
+            (Item::Data(width, data), _) => {
+                let mut bytes = vec![];
+                for data in data {
+                    match data {
+                        Data::Value(value) => {
+                            bytes.extend(data_bytes(value, *width, labels, ins_addr)?)
                         }
-                    };
-                    b_ext(&mut result, op.ins_addr, &[op_code, addr]);
+                        Data::Text(text) => bytes.extend(text),
+                    }
+                }
+                b_ext(&mut result, ins_addr, &bytes);
+                continue;
+            }
+            (Item::Fill(_, Some(fill)) | Item::Align(_, Some(fill)), _) => {
+                let byte = data_bytes(fill, 1, labels, ins_addr)?[0];
+                b_ext(&mut result, ins_addr, &vec![byte; placed.size]);
+                continue;
+            }
+            (Item::Instruction(op), Some(operand)) => (op, operand),
+            _ => continue,
+        };
+        match addr {
+            AddressType::Impl | AddressType::Accumulator => {
+                if let Some(value) = value {
+                    return Err(AsmError::new("Unexpected value", Some(value.symbol)));
+                } else if let Some(op_code) = variant.op_code(&op.instruct, &AddressType::Impl) {
+                    b_ext(&mut result, ins_addr, &[op_code]);
+                } else if let Some(op_code) =
+                    variant.op_code(&op.instruct, &AddressType::Accumulator)
+                {
+                    b_ext(&mut result, ins_addr, &[op_code]);
                 } else {
                     return Err(AsmError::new(
-                        "Instruction doesn't allow this type of addressing",
-                        Some(op.symbol),
+                        &format!("Instruction \"{:?}\" needs an address", op.instruct),
+                        Some(op.symbol.clone()),
                     ));
                 }
             }
-            InterAddr::Addr(addr, value) => match addr {
-                AddressType::Immediate
-                | AddressType::IndirectX
-                | AddressType::IndirectY
-                | AddressType::ZeroPage
-                | AddressType::ZeroPageX
-                | AddressType::ZeroPageY => {
-                    if let Some(value) = value {
-                        let op_code = match op.instruct.get_op_code(&addr) {
-                            Some(val) => val,
-                            None => {
-                                return Err(AsmError::new(
-                                    &format!(
-                                        "Invalid addres type for instruction {}",
-                                        op.symbol.text
-                                    ),
-                                    Some(value.symbol),
-                                ))
-                            }
-                        };
-
-                        b_ext(&mut result, op.ins_addr, &[op_code, value.value as u8]);
-                    } else {
-                        return Err(AsmError::new("Missing value", Some(op.symbol)));
-                    }
-                }
-                AddressType::Impl | AddressType::Accumulator => {
-                    if let Some(value) = value {
-                        return Err(AsmError::new("Unexpected value", Some(value.symbol)));
-                    } else if let Some(op_code) = op.instruct.get_op_code(&AddressType::Impl) {
-                        b_ext(&mut result, op.ins_addr, &[op_code]);
-                    } else if let Some(op_code) = op.instruct.get_op_code(&AddressType::Accumulator) {
-                        b_ext(&mut result, op.ins_addr, &[op_code]);
-                    } else {
+            _ => {
+                let Some(value) = value else {
+                    return Err(AsmError::new("Missing value", Some(op.symbol.clone())));
+                };
+                let op_code = match variant.op_code(&op.instruct, &addr) {
+                    Some(val) => val,
+                    None => {
                         return Err(AsmError::new(
-                            &format!("Instruction \"{:?}\" needs an address", op.instruct),
-                            Some(op.symbol),
-                        ));
+                            &format!("Invalid addres type for instruction {}", op.symbol.text),
+                            Some(value.symbol),
+                        ))
                     }
-                }
-                AddressType::Indirect
-                | AddressType::Absolute
-                | AddressType::AbsoluteX
-                | AddressType::AbsoluteY => {
-                    if let Some(value) = value {
-                        let op_code = match op.instruct.get_op_code(&addr) {
-                            Some(val) => val,
-                            None => {
-                                return Err(AsmError::new(
-                                    &format!(
-                                        "Invalid addres type for instruction {}",
-                                        op.symbol.text
-                                    ),
-                                    Some(value.symbol),
-                                ))
-                            }
-                        };
-                        let op_addr = value.value;
-                        if op_addr > u16::MAX as i32 {
-                            panic!("Absolute address doesnt fit in u16: {op_addr}");
-                        }
-                        let full_addr = op_addr as u16;
-                        let low: u8 = ((full_addr & 0xFF00) >> 8) as u8;
-                        let high: u8 = (full_addr & 0x00FF) as u8;
-
-                        b_ext(&mut result, op.ins_addr, &[op_code, high, low]);
-                    } else {
-                        return Err(AsmError::new("Missing value", Some(op.symbol)));
+                };
+                let number = value.eval(labels, ins_addr)?;
+                let range = match addr {
+                    AddressType::Relative => {
+                        let addr = relative(number, ins_addr, &value.symbol)?;
+                        b_ext(&mut result, ins_addr, &[op_code, addr]);
+                        continue;
                     }
+                    // negative immediates are written as their two's complement
+                    AddressType::Immediate => -0x80..=0xFF,
+                    AddressType::IndirectX
+                    | AddressType::IndirectY
+                    | AddressType::ZeroPageIndirect
+                    | AddressType::ZeroPage
+                    | AddressType::ZeroPageX
+                    | AddressType::ZeroPageY => 0..=0xFF,
+                    _ => 0..=0xFFFF,
+                };
+                if !range.contains(&number) {
+                    return Err(AsmError::new(
+                        &format!("Value {number} doesnt fit in {:?}", addr),
+                        Some(value.symbol),
+                    ));
                 }
-                _ => unimplemented!("{:?}", addr),
-            },
-        };
+                let [low, high, ..] = number.to_le_bytes();
+                if *range.end() == 0xFF {
+                    b_ext(&mut result, ins_addr, &[op_code, low]);
+                } else {
+                    b_ext(&mut result, ins_addr, &[op_code, low, high]);
+                }
+            }
+        }
     }
     return Ok(result);
 }
+
+// the branch offset from the instruction at `ins_addr` to `target`
+fn relative(target: i32, ins_addr: u16, symbol: &Symbol) -> Result<u8, AsmError> {
+    let diff = target - (ins_addr as i32) - 2;
+    match i8::try_from(diff) {
+        Ok(val) => Ok(val as u8),
+        Err(_) => Err(AsmError::new(
+            &format!("Relative address doesnt fit in i8: {diff}"),
+            Some(symbol.clone()),
+        )),
+    }
+}
@@ -0,0 +1,178 @@
+// The files a program is assembled from.
+//
+// `.include "file"` is replaced by the tokens of the file before anything else is parsed, so
+// the defines and labels of an included file are seen as if they were written in its place.
+// `.incbin` reads its file when the directive is parsed. A file name is looked up next to the
+// file that names it first, then in the include paths in order.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::asm::lexer::{lex_file, unescape, Token, TokenType};
+use crate::asm::{AsmError, Pos, Symbol};
+
+#[derive(Debug)]
+pub struct Source {
+    pub name: String,
+    // where it was read from, lines given straight to the assembler have none
+    pub path: Option<PathBuf>,
+    pub lines: Vec<String>,
+}
+
+// the files by index, the `file` of a `Pos` is an index into `files`
+#[derive(Debug, Default)]
+pub struct Sources {
+    pub files: Vec<Source>,
+    pub include_paths: Vec<PathBuf>,
+}
+
+impl Sources {
+    pub fn new(include_paths: Vec<PathBuf>) -> Sources {
+        Sources {
+            files: vec![],
+            include_paths,
+        }
+    }
+
+    pub fn add(&mut self, name: String, path: Option<PathBuf>, lines: Vec<String>) -> usize {
+        self.files.push(Source { name, path, lines });
+        self.files.len() - 1
+    }
+
+    pub fn tokens(&self, file: usize) -> Result<Vec<Token>, AsmError> {
+        lex_file(self.files[file].lines.iter(), file)
+    }
+
+    pub fn name(&self, file: usize) -> &str {
+        self.files
+            .get(file)
+            .map_or("STDIN", |source| source.name.as_str())
+    }
+
+    // the source line a position is on, for error messages
+    pub fn line(&self, pos: &Pos) -> Option<&str> {
+        let source = self.files.get(pos.file)?;
+        source
+            .lines
+            .get(pos.line.checked_sub(1)?)
+            .map(|line| line.as_str())
+    }
+
+    // finds the file named by a string token
+    pub fn resolve(&self, name: &Symbol) -> Result<PathBuf, AsmError> {
+        let text = file_name(name)?;
+        let beside = self
+            .files
+            .get(name.start.file)
+            .and_then(|source| source.path.as_deref())
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""));
+        std::iter::once(beside)
+            .chain(self.include_paths.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(&text))
+            .find(|path| path.is_file())
+            .ok_or_else(|| AsmError::new(&format!("Cannot find {text}"), Some(name.clone())))
+    }
+
+    pub fn read_binary(&self, name: &Symbol) -> Result<Vec<u8>, AsmError> {
+        let path = self.resolve(name)?;
+        fs::read(&path).map_err(|e| {
+            AsmError::new(
+                &format!("Cannot read {}: {e}", path.display()),
+                Some(name.clone()),
+            )
+        })
+    }
+
+    // replaces every `.include "file"` with the tokens of the file, included files can
+    // include others but not one that is already being included
+    pub fn expand(&mut self, tokens: Vec<Token>) -> Result<Vec<Token>, AsmError> {
+        let root = tokens
+            .first()
+            .and_then(|token| self.files.get(token.symbol.start.file))
+            .and_then(|source| source.path.as_ref())
+            .and_then(|path| path.canonicalize().ok());
+        let mut stack: Vec<PathBuf> = root.into_iter().collect();
+        self.expand_in(tokens, &mut stack)
+    }
+
+    fn expand_in(
+        &mut self,
+        tokens: Vec<Token>,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Token>, AsmError> {
+        let mut expanded = Vec::with_capacity(tokens.len());
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            let is_include = token.token == TokenType::Dot
+                && tokens.peek().is_some_and(|next| {
+                    next.token == TokenType::Identifier
+                        && next.symbol.text.eq_ignore_ascii_case("include")
+                });
+            if !is_include {
+                expanded.push(token);
+                continue;
+            }
+            let directive = tokens.next().unwrap();
+            let name = match tokens.next() {
+                Some(Token {
+                    token: TokenType::String,
+                    symbol,
+                }) => symbol,
+                other => {
+                    return Err(AsmError::new(
+                        "Expected a file name after .include",
+                        Some(other.map_or(directive.symbol, |t| t.symbol)),
+                    ))
+                }
+            };
+            if let Some(extra) = tokens.next_if(|t| t.token != TokenType::NewLine) {
+                return Err(AsmError::new(
+                    &format!("Unexpected {} after the file name", extra.symbol.text),
+                    Some(extra.symbol),
+                ));
+            }
+
+            let path = self.resolve(&name)?;
+            let unreadable = |e: std::io::Error| {
+                AsmError::new(
+                    &format!("Cannot read {}: {e}", path.display()),
+                    Some(name.clone()),
+                )
+            };
+            let canonical = path.canonicalize().map_err(unreadable)?;
+            if stack.contains(&canonical) {
+                let chain: Vec<String> = stack
+                    .iter()
+                    .skip_while(|p| **p != canonical)
+                    .chain([&canonical])
+                    .map(|p| {
+                        p.file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .into_owned()
+                    })
+                    .collect();
+                return Err(AsmError::new(
+                    &format!("Include cycle: {}", chain.join(" -> ")),
+                    Some(name.clone()),
+                ));
+            }
+            let text = fs::read_to_string(&path).map_err(unreadable)?;
+            let lines = text.lines().map(|l| l.to_string()).collect();
+            let file = self.add(path.display().to_string(), Some(path), lines);
+            stack.push(canonical);
+            let included = self.expand_in(self.tokens(file)?, stack)?;
+            stack.pop();
+            expanded.extend(included);
+        }
+        Ok(expanded)
+    }
+}
+
+fn file_name(symbol: &Symbol) -> Result<String, AsmError> {
+    unescape(&symbol.text)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| AsmError::new("Invalid file name", Some(symbol.clone())))
+}
@@ -1,19 +1,37 @@
-use std::io;
 use std::env;
-use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
 
-use rs6502::asm::assemble;
+use rs6502::asm::{assemble_file, try_assemble_source};
+use rs6502::instruct::Variant;
 
 fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let lines: Vec<String> = if args.len() < 2 {
+    let mut args: Vec<String> = env::args().collect();
+    let mut variant = Variant::Nmos6502;
+    if let Some(i) = args.iter().position(|arg| arg == "--cpu") {
+        let name = args.get(i + 1).expect("Missing cpu name");
+        variant = Variant::from_str(name).expect("Unknown cpu, use 6502, 65C02, 2A03 or 6510");
+        args.drain(i..i + 2);
+    }
+    // -I DIR adds a directory to look for included files in, it can be repeated
+    let mut include_paths = Vec::new();
+    while let Some(i) = args.iter().position(|arg| arg == "-I") {
+        let dir = args.get(i + 1).expect("Missing include directory");
+        include_paths.push(PathBuf::from(dir));
+        args.drain(i..i + 2);
+    }
+    let res = if args.len() < 2 {
         let stdin = io::stdin();
-        stdin.lines().map(|l| l.unwrap()).collect()
+        let lines = stdin.lines().map(|l| l.unwrap()).collect();
+        try_assemble_source(lines, None, variant, &include_paths)
     } else {
-        let file_name = &args[1];
-        fs::read_to_string(file_name).expect("Invalid file").lines().map(|v| v.to_string()).collect()
+        assemble_file(Path::new(&args[1]), variant, &include_paths)
     };
-    let res = assemble(lines);
+    let res = res.unwrap_or_else(|report| {
+        eprint!("{report}");
+        process::exit(1);
+    });
 
     for high in 0x000..=0xFFF {
         let mut line: [u8; 0x10] = [0; 0x10];
@@ -22,14 +40,14 @@ fn main() -> io::Result<()> {
             if let Some(byte) = res.get(&(low + (high * 0x10))) {
                 line[low as usize] = *byte;
                 has_byte = true;
-            } 
+            }
         }
         if has_byte {
             print!("{:04x}: ", high * 0x10);
             for byte in line {
                 print!("{:02x} ", byte);
             }
-            print!("\n");
+            println!();
         }
     }
     Ok(())
@@ -0,0 +1,175 @@
+// Loads program images, runs them headless and prints where they stopped.
+//
+//     rs6502-run [options] FILE[@ADDR]...
+//
+//     --cpu NAME          6502, 65C02, 2A03 or 6510
+//     --reset ADDR        sets the reset vector
+//     --start ADDR        starts at ADDR after the reset sequence instead
+//     --until ADDR        stops when the program gets to ADDR
+//     --max-cycles N      stops after N cycles
+//     --dump START:END    prints the memory in the range once stopped, can be repeated
+//     -I DIR              adds a directory to look for included files in, can be repeated
+//
+// Files ending in .asm are assembled, with their includes looked up next to them and then in
+// the -I directories. .prg and .hex/.ihex are PRG and Intel HEX images, anything else is a
+// raw binary. Addresses are hex, with an optional `$` or `0x`. The exit status is 0 for a
+// BRK or a `jmp *` trap, or only for the `--until` address when it is given, 1 for any other
+// stop and 2 when the arguments or files are invalid.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use rs6502::asm::try_assemble_source;
+use rs6502::fast::run_instruction;
+use rs6502::instruct::Variant;
+use rs6502::loader::{load, Format};
+use rs6502::m6502::State;
+use rs6502::memory::{DefaultMemory, Memory};
+use rs6502::runner::{run_until, Limits, Stop};
+
+const USAGE: &str = "usage: rs6502-run [--cpu NAME] [--reset ADDR] [--start ADDR] [--until ADDR] \
+                     [--max-cycles N] [--dump START:END]... [-I DIR]... FILE[@ADDR]...";
+
+fn fail(reason: &str) -> ! {
+    eprintln!("{reason}");
+    process::exit(2);
+}
+
+fn parse_addr(text: &str) -> u16 {
+    let digits = text
+        .strip_prefix('$')
+        .or_else(|| text.strip_prefix("0x"))
+        .unwrap_or(text);
+    u16::from_str_radix(digits, 16).unwrap_or_else(|_| fail(&format!("invalid address {text}")))
+}
+
+fn load_file(memory: &mut DefaultMemory, arg: &str, variant: Variant, include_paths: &[PathBuf]) {
+    let (path, addr) = match arg.rsplit_once('@') {
+        Some((path, addr)) => (path, Some(parse_addr(addr))),
+        None => (arg, None),
+    };
+    let data = fs::read(path).unwrap_or_else(|e| fail(&format!("{path}: {e}")));
+    let is_asm = Path::new(path)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("asm"));
+    if is_asm {
+        if addr.is_some() {
+            fail(&format!("{path}: assembly sets its own address with .ORG"));
+        }
+        let text = String::from_utf8_lossy(&data);
+        let lines = text.lines().map(|l| l.to_string()).collect();
+        let program = try_assemble_source(lines, Some(Path::new(path)), variant, include_paths)
+            .unwrap_or_else(|report| {
+                eprint!("{report}");
+                process::exit(2);
+            });
+        for (key, val) in program {
+            memory.set(key, val);
+        }
+    } else if let Err(e) = load(&data, Format::from_path(path), addr, memory) {
+        fail(&format!("{path}: {e}"));
+    }
+}
+
+fn dump(memory: &DefaultMemory, start: u16, end: u16) {
+    for line in (start..=end).step_by(0x10) {
+        print!("{line:04x}:");
+        for addr in line..=line.saturating_add(0xF).min(end) {
+            print!(" {:02x}", memory.get(addr));
+        }
+        println!();
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut variant = Variant::Nmos6502;
+    let mut reset = None;
+    let mut start = None;
+    let mut limits = Limits::default();
+    let mut dumps = Vec::new();
+    let mut files = Vec::new();
+    let mut include_paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{arg} needs a value")))
+        };
+        match arg.as_str() {
+            "--cpu" => {
+                let name = value();
+                variant = Variant::from_str(&name)
+                    .unwrap_or_else(|| fail("Unknown cpu, use 6502, 65C02, 2A03 or 6510"));
+            }
+            "--reset" => reset = Some(parse_addr(&value())),
+            "--start" => start = Some(parse_addr(&value())),
+            "--until" => limits.until = Some(parse_addr(&value())),
+            "--max-cycles" => {
+                let cycles = value();
+                limits.max_cycles = Some(
+                    cycles
+                        .parse()
+                        .unwrap_or_else(|_| fail(&format!("invalid cycle count {cycles}"))),
+                );
+            }
+            "--dump" => {
+                let range = value();
+                let Some((from, to)) = range.split_once(':') else {
+                    fail(&format!("invalid range {range}, use START:END"));
+                };
+                dumps.push((parse_addr(from), parse_addr(to)));
+            }
+            "-I" => include_paths.push(PathBuf::from(value())),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ if arg.starts_with("--") => fail(&format!("unknown option {arg}\n{USAGE}")),
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        fail(USAGE);
+    }
+
+    let mut memory = DefaultMemory::new();
+    for file in &files {
+        load_file(&mut memory, file, variant, &include_paths);
+    }
+    if let Some(addr) = reset {
+        let [low, high] = addr.to_le_bytes();
+        memory.set(0xFFFC, low);
+        memory.set(0xFFFD, high);
+    }
+
+    let mut state = State::new();
+    state.variant = variant;
+    if let Some(addr) = start {
+        run_instruction(&mut state, &mut memory);
+        state.registers.pc = addr;
+    }
+    let stop = run_until(&mut state, &mut memory, &limits);
+
+    let registers = &state.registers;
+    println!("stopped: {stop}");
+    println!(
+        "pc {:04x} a {:02x} x {:02x} y {:02x} sp {:02x} sr {} cycles {}",
+        registers.pc,
+        registers.ac,
+        registers.xr,
+        registers.yr,
+        registers.sp,
+        registers.fmt_status(),
+        state.total_cycles
+    );
+    for (from, to) in dumps {
+        dump(&memory, from, to);
+    }
+    let passed = match limits.until {
+        Some(_) => matches!(stop, Stop::Reached(_)),
+        None => matches!(stop, Stop::Brk(_) | Stop::Trap(_)),
+    };
+    process::exit(if passed { 0 } else { 1 });
+}
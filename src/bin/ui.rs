@@ -1,25 +1,52 @@
 use iced::alignment::Vertical;
-use iced::widget::{button, checkbox, column, container, row, scrollable, text, Column, Row};
-use iced::Color;
+use iced::keyboard;
+use iced::widget::{
+    button, checkbox, column, container, pick_list, row, scrollable, text, Column, Row,
+};
+use iced::{Color, Subscription};
 
 use rs6502::asm::{assemble, read_lines};
-use rs6502::m6502::{step, State};
+use rs6502::history::History;
+use rs6502::input::{Input, Recording, Replay};
+use rs6502::instruct::Variant;
+use rs6502::m6502::State;
 use rs6502::memory::{DefaultMemory, Memory};
-use rs6502::instruct::Instruct;
+use rs6502::snapshot::Snapshot;
 
 use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SNAPSHOT_FILE: &str = "snapshot.rs6502";
+const RECORDING_FILE: &str = "recording.txt";
+// half cycles that can be stepped back
+const HISTORY_SIZE: usize = 100_000;
+// the devices the example programs use, a new random number every cycle and the last key
+const RANDOM: u16 = 0xFE;
+const KEY: u16 = 0xFF;
 
 pub fn main() -> iced::Result {
-    iced::run("Emulator", Machine::update, Machine::view)
+    iced::application("Emulator", Machine::update, Machine::view)
+        .subscription(Machine::subscription)
+        .run()
 }
 
 struct Machine {
     memory: DefaultMemory,
     state: State,
     last_states: Vec<Rc<State>>,
+    history: History,
+    // the inputs since the start or the last snapshot load, the ones after the current point
+    // are replayed when stepping forward
+    recording: Recording,
+    // the machine the recording starts from
+    start: Snapshot,
+    // state of the random number generator
+    seed: u32,
     curr_page: u8,
     follow_ab: bool,
     follow_pc: bool,
+    // result of the last file save or load
+    message: String,
 }
 
 impl Default for Machine {
@@ -37,13 +64,19 @@ impl Default for Machine {
         for (key, val) in res {
             memory.set(key, val);
         }
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
         Machine {
+            start: Snapshot::new(&state, &memory),
+            seed: time.subsec_nanos() | 1,
             state,
             last_states: Vec::new(),
+            history: History::new(HISTORY_SIZE),
+            recording: Recording::new(),
             memory,
             curr_page: 0,
             follow_ab: false,
             follow_pc: false,
+            message: String::new(),
         }
     }
 }
@@ -103,9 +136,23 @@ fn page_widget(memory: &dyn Memory, curr_page: u8, ab: u16, pc: u16) -> Column<'
 enum Message {
     HalfStep,
     Step,
+    HalfStepBack,
+    StepBack,
+    InstructionBack,
     ClearStates,
+    SaveSnapshot,
+    LoadSnapshot,
+    SaveRecording,
+    ReplayRecording,
     ChangePage(i8),
     ToggleReset(bool),
+    ToggleIrq(bool),
+    ToggleNmi(bool),
+    KeyPress(u8),
+    ToggleReady(bool),
+    ToggleSetOverflow(bool),
+    ToggleUndocumented(bool),
+    SelectVariant(Variant),
     ToggleFollowAB(bool),
     ToggleFollowPC(bool),
 }
@@ -114,37 +161,97 @@ impl Machine {
     fn update(&mut self, message: Message) {
         match message {
             Message::HalfStep => {
-                if self.state.clock1 {
-                    self.last_states.push(Rc::new(self.state.clone()));
-                    step(&mut self.state)
-                } else {
-                    self.last_states.push(Rc::new(self.state.clone()));
-                    if self.state.rw {
-                        self.state.db = self.memory.get(self.state.ab);
-                    } else {
-                        self.memory.set(self.state.ab, self.state.db);
-                    }
-                    step(&mut self.state);
-                }
+                self.half_step();
             }
             Message::Step => {
-                self.last_states.push(Rc::new(self.state.clone()));
                 if self.state.clock1 {
-                    step(&mut self.state)
+                    self.half_step();
                 }
-                self.last_states.push(Rc::new(self.state.clone()));
-                if self.state.rw {
-                    self.state.db = self.memory.get(self.state.ab);
-                } else {
-                    self.memory.set(self.state.ab, self.state.db);
+                self.half_step();
+            }
+            Message::HalfStepBack => {
+                if self
+                    .history
+                    .half_step_back(&mut self.state, &mut self.memory)
+                {
+                    self.last_states.pop();
                 }
-                step(&mut self.state);
+            }
+            Message::StepBack => {
+                let undone = self.history.cycle_back(&mut self.state, &mut self.memory);
+                self.forget_states(undone);
+            }
+            Message::InstructionBack => {
+                let undone = self
+                    .history
+                    .instruction_back(&mut self.state, &mut self.memory);
+                self.forget_states(undone);
             }
             Message::ClearStates => {
                 self.last_states.clear();
             }
+            Message::SaveSnapshot => {
+                self.message = match Snapshot::new(&self.state, &self.memory).save(SNAPSHOT_FILE) {
+                    Ok(()) => format!("saved {SNAPSHOT_FILE}"),
+                    Err(error) => format!("can't save {SNAPSHOT_FILE}: {error}"),
+                };
+            }
+            Message::LoadSnapshot => {
+                self.message = match Snapshot::load(SNAPSHOT_FILE) {
+                    Ok(snapshot) => {
+                        self.start = snapshot.clone();
+                        self.state = snapshot.state;
+                        self.memory = snapshot.memory;
+                        self.last_states.clear();
+                        self.history.clear();
+                        self.recording = Recording::new();
+                        format!("loaded {SNAPSHOT_FILE}")
+                    }
+                    Err(error) => format!("can't load {SNAPSHOT_FILE}: {error}"),
+                };
+            }
+            Message::SaveRecording => {
+                self.message = match self.recording.save(RECORDING_FILE) {
+                    Ok(()) => format!("saved {RECORDING_FILE}"),
+                    Err(error) => format!("can't save {RECORDING_FILE}: {error}"),
+                };
+            }
+            Message::ReplayRecording => {
+                self.message = match Recording::load(RECORDING_FILE) {
+                    Ok(recording) => {
+                        self.state = self.start.state.clone();
+                        self.memory = self.start.memory.clone();
+                        self.last_states.clear();
+                        self.history.clear();
+                        self.recording = recording;
+                        format!("replaying {RECORDING_FILE}")
+                    }
+                    Err(error) => format!("can't load {RECORDING_FILE}: {error}"),
+                };
+            }
             Message::ToggleReset(is_checked) => {
-                self.state.res = is_checked;
+                self.input(Input::Res(is_checked));
+            }
+            Message::ToggleIrq(is_checked) => {
+                self.input(Input::Irq(is_checked));
+            }
+            Message::ToggleNmi(is_checked) => {
+                self.input(Input::Nmi(is_checked));
+            }
+            Message::KeyPress(key) => {
+                self.input(Input::Write(KEY, key));
+            }
+            Message::ToggleReady(is_checked) => {
+                self.input(Input::Rdy(is_checked));
+            }
+            Message::ToggleSetOverflow(is_checked) => {
+                self.input(Input::So(is_checked));
+            }
+            Message::ToggleUndocumented(is_checked) => {
+                self.state.undocumented = is_checked;
+            }
+            Message::SelectVariant(variant) => {
+                self.state.variant = variant;
             }
             Message::ToggleFollowAB(is_checked) => {
                 self.follow_ab = is_checked;
@@ -166,8 +273,50 @@ impl Machine {
         }
     }
 
-    fn view(&self) -> Row<Message> {
-        println!("{:?}", self.state);
+    // drops the rows of the half cycles that were stepped back
+    fn forget_states(&mut self, undone: usize) {
+        let len = self.last_states.len().saturating_sub(undone);
+        self.last_states.truncate(len);
+    }
+
+    // the recorded inputs are replayed up to the end of the recording, the devices take over
+    // from there
+    fn half_step(&mut self) {
+        self.last_states.push(Rc::new(self.state.clone()));
+        let mut replay = Replay::new(&self.recording);
+        replay.seek(&self.state);
+        if !replay.is_done() {
+            replay.apply(&mut self.state, &mut self.memory);
+        } else if self.state.clock1 {
+            let random = self.random();
+            self.input(Input::Write(RANDOM, random));
+        }
+        self.history.half_step(&mut self.state, &mut self.memory);
+    }
+
+    // an input replaces what was recorded after the current point
+    fn input(&mut self, input: Input) {
+        self.recording.rewind(&self.state);
+        self.recording
+            .input(&mut self.state, &mut self.memory, input);
+    }
+
+    // xorshift
+    fn random(&mut self) -> u8 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as u8
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        keyboard::on_key_press(|key, _| match key.as_ref() {
+            keyboard::Key::Character(c) if c.is_ascii() => c.bytes().next().map(Message::KeyPress),
+            _ => None,
+        })
+    }
+
+    fn view(&self) -> Row<'_, Message> {
         row![
             column![
                 row![
@@ -179,7 +328,50 @@ impl Machine {
                     button("Half step").on_press(Message::HalfStep),
                     button("Clear states").on_press(Message::ClearStates),
                 ],
+                row![
+                    button("Step back")
+                        .on_press_maybe((!self.history.is_empty()).then_some(Message::StepBack)),
+                    button("Half step back").on_press_maybe(
+                        (!self.history.is_empty()).then_some(Message::HalfStepBack)
+                    ),
+                    button("Instruction back").on_press_maybe(
+                        (!self.history.is_empty()).then_some(Message::InstructionBack)
+                    ),
+                ],
+                row![
+                    button("Save snapshot").on_press(Message::SaveSnapshot),
+                    button("Load snapshot").on_press(Message::LoadSnapshot),
+                    button("Save recording").on_press(Message::SaveRecording),
+                    button("Replay recording").on_press(Message::ReplayRecording),
+                    text(&self.message),
+                ]
+                .spacing(4)
+                .align_y(Vertical::Center),
                 checkbox("Reset signal", self.state.res).on_toggle(Message::ToggleReset),
+                row![
+                    checkbox("Interrupt (IRQ)", self.state.irq).on_toggle(Message::ToggleIrq),
+                    checkbox("Non-maskable interrupt (NMI)", self.state.nmi)
+                        .on_toggle(Message::ToggleNmi),
+                ]
+                .spacing(4),
+                row![
+                    checkbox("Ready (RDY)", self.state.rdy).on_toggle(Message::ToggleReady),
+                    checkbox("Set overflow (SO)", self.state.so)
+                        .on_toggle(Message::ToggleSetOverflow),
+                ]
+                .spacing(4),
+                checkbox("Undocumented opcodes", self.state.undocumented)
+                    .on_toggle(Message::ToggleUndocumented),
+                pick_list(
+                    Variant::ALL,
+                    Some(self.state.variant),
+                    Message::SelectVariant
+                ),
+                text(
+                    self.state
+                        .fault
+                        .map_or(String::new(), |fault| fault.to_string())
+                ),
                 row![
                     checkbox("Follow address bus", self.follow_ab)
                         .on_toggle(Message::ToggleFollowAB),
@@ -255,11 +447,15 @@ impl Machine {
                 )
                 .spacing(VERT_SPACING),
             column![text("Ins")]
-                .extend(
-                    input
-                        .clone()
-                        .map(|state| text(Instruct::from_op_code(state.ir).map_or("", |i| i.instruction.to_str())).into())
-                )
+                .extend(input.clone().map(|state| {
+                    text(
+                        state
+                            .variant
+                            .decode(state.ir)
+                            .map_or("", |i| i.instruction.to_str()),
+                    )
+                    .into()
+                }))
                 .spacing(VERT_SPACING),
             column![text("State")]
                 .extend(
@@ -289,6 +485,34 @@ impl Machine {
                         .map(|state| text(format!("{:02x}", state.db)).into())
                 )
                 .spacing(VERT_SPACING),
+            column![text("R/W")]
+                .extend(
+                    input
+                        .clone()
+                        .map(|state| text(if state.rw { "R" } else { "W" }).into())
+                )
+                .spacing(VERT_SPACING),
+            column![text("SYNC")]
+                .extend(
+                    input
+                        .clone()
+                        .map(|state| text(format!("{}", state.sync as u8)).into())
+                )
+                .spacing(VERT_SPACING),
+            column![text("RDY")]
+                .extend(
+                    input
+                        .clone()
+                        .map(|state| text(format!("{}", state.rdy as u8)).into())
+                )
+                .spacing(VERT_SPACING),
+            column![text("SO")]
+                .extend(
+                    input
+                        .clone()
+                        .map(|state| text(format!("{}", state.so as u8)).into())
+                )
+                .spacing(VERT_SPACING),
         ]
         .spacing(8)
     }
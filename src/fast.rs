@@ -0,0 +1,332 @@
+// Instruction level executor, runs a whole instruction per call instead of a half cycle.
+//
+// It works on the same `State` as the cycle level core and always leaves it on an
+// instruction boundary, so both can be mixed freely. The dummy bus cycles are skipped
+// and the input pins are only sampled between instructions.
+
+use crate::instruct::{AddressType, Instruct, InstructionInfo};
+use crate::m6502::{
+    branch_taken, bus_read, bus_write, decimal_cycle, execute, indirect_high, is_rmw, lookup,
+    modify, pointer_high, poll_interrupts, run_instruction as run_cycle_level, sample_so,
+    unstable_store, waiting, zero_page_index, Fault, IoPort, State, TimingState, DECIMAL,
+    INTERRUPT, IRQ_VECTOR, NMI_VECTOR, RESET_VECTOR,
+};
+use crate::memory::Bus;
+
+// runs one instruction (or interrupt sequence), returns the cycles it takes on the real chip
+pub fn run_instruction<B: Bus + ?Sized>(state: &mut State, bus: &mut B) -> u32 {
+    if state.res {
+        state.resetting = true;
+        state.interrupt = false;
+        state.fault = None;
+        state.waiting = false;
+        state.stalled = false;
+        state.port = IoPort::new();
+        return 0;
+    }
+    sample_so(state);
+    if state.fault.is_some() || waiting(state) {
+        return 0;
+    }
+    if !state.clock1 || !state.next_timing.is_fetch() || !state.rdy {
+        // finish the instruction the cycle level core is in the middle of, it also does the
+        // RDY stalls
+        return run_cycle_level(state, bus);
+    }
+
+    if state.nmi && !state.nmi_prev {
+        state.nmi_pending = true;
+    }
+    state.nmi_prev = state.nmi;
+
+    let cycles = if state.interrupt || state.resetting {
+        interrupt(state, bus)
+    } else {
+        let op_code = bus_read(state, bus, state.registers.pc);
+        state.registers.pc = state.registers.pc.wrapping_add(1);
+        state.ir = op_code;
+        let Some(info) = lookup(state, op_code) else {
+            // only the opcode fetch happens before the processor jams
+            state.fault = Some(Fault {
+                pc: state.registers.pc.wrapping_sub(1),
+                op_code,
+            });
+            state.total_cycles += 1;
+            state.half_cycles += 2;
+            return 1;
+        };
+        let sr = state.registers.sr;
+        let cycles = instruction(state, bus, info);
+        if op_code != 0 {
+            // these change the flag on their last cycle, after the interrupts were polled
+            let registers = state.registers.sr;
+            if matches!(
+                info.instruction,
+                Instruct::CLI | Instruct::SEI | Instruct::PLP
+            ) {
+                state.registers.sr = (registers & !INTERRUPT) | (sr & INTERRUPT);
+            }
+            poll_interrupts(state);
+            state.registers.sr = registers;
+        }
+        cycles
+    };
+
+    state.cycles = cycles;
+    state.total_cycles += cycles as u64;
+    state.half_cycles += 2 * cycles as u64;
+    state.timing = TimingState::new();
+    state.next_timing = TimingState::new();
+    state.clock1 = true;
+    state.clock2 = false;
+    state.sync = false;
+    cycles as u32
+}
+
+// runs whole instructions until at least `cycles` have passed, returns the cycles taken
+pub fn run_cycles<B: Bus + ?Sized>(state: &mut State, bus: &mut B, cycles: u32) -> u32 {
+    let mut total = 0;
+    while total < cycles {
+        let taken = run_instruction(state, bus);
+        if taken == 0 {
+            break;
+        }
+        total += taken;
+    }
+    total
+}
+
+fn read_word<B: Bus + ?Sized>(state: &State, bus: &mut B, low: u16, high: u16) -> u16 {
+    bus_read(state, bus, low) as u16 | ((bus_read(state, bus, high) as u16) << 8)
+}
+
+fn fetch<B: Bus + ?Sized>(state: &mut State, bus: &mut B) -> u8 {
+    let value = bus_read(state, bus, state.registers.pc);
+    state.registers.pc = state.registers.pc.wrapping_add(1);
+    value
+}
+
+fn fetch_word<B: Bus + ?Sized>(state: &mut State, bus: &mut B) -> u16 {
+    let low = fetch(state, bus) as u16;
+    low | ((fetch(state, bus) as u16) << 8)
+}
+
+fn push<B: Bus + ?Sized>(state: &mut State, bus: &mut B, value: u8) {
+    bus_write(state, bus, 0x0100 | state.registers.sp as u16, value);
+    state.registers.sp = state.registers.sp.wrapping_sub(1);
+}
+
+fn pull<B: Bus + ?Sized>(state: &mut State, bus: &mut B) -> u8 {
+    state.registers.sp = state.registers.sp.wrapping_add(1);
+    bus_read(state, bus, 0x0100 | state.registers.sp as u16)
+}
+
+// BRK, IRQ, NMI and RESET all share the same sequence
+fn interrupt<B: Bus + ?Sized>(state: &mut State, bus: &mut B) -> u8 {
+    let brk = !state.interrupt && !state.resetting;
+    if state.resetting {
+        state.registers.sp = state.registers.sp.wrapping_sub(3);
+    } else {
+        let pc = state.registers.pc;
+        push(state, bus, (pc >> 8) as u8);
+        push(state, bus, pc as u8);
+        let sr = state.registers.status_push(brk);
+        push(state, bus, sr);
+    }
+    state.registers.status_add(INTERRUPT);
+    if state.variant.is_cmos() {
+        state.registers.status_remove(DECIMAL);
+    }
+    let vector = if state.resetting {
+        RESET_VECTOR
+    } else if state.nmi_pending {
+        state.nmi_pending = false;
+        NMI_VECTOR
+    } else {
+        IRQ_VECTOR
+    };
+    state.registers.pc = read_word(state, bus, vector, vector + 1);
+    state.interrupt = false;
+    state.resetting = false;
+    state.ir = 0;
+    7
+}
+
+fn instruction<B: Bus + ?Sized>(state: &mut State, bus: &mut B, info: &InstructionInfo) -> u8 {
+    let InstructionInfo {
+        instruction,
+        mode,
+        cycles,
+        extra_cycles,
+    } = info;
+    let mut cycles = *cycles;
+    // the indexed address before the carry into the high byte
+    let mut unfixed = 0;
+
+    let addr = match mode {
+        AddressType::Impl | AddressType::Accumulator => 0,
+        AddressType::Immediate | AddressType::Relative => {
+            let addr = state.registers.pc;
+            state.registers.pc = state.registers.pc.wrapping_add(1);
+            addr
+        }
+        AddressType::ZeroPage => fetch(state, bus) as u16,
+        AddressType::ZeroPageX => {
+            let base = fetch(state, bus);
+            zero_page_index(state, base, state.registers.xr)
+        }
+        AddressType::ZeroPageY => {
+            let base = fetch(state, bus);
+            zero_page_index(state, base, state.registers.yr)
+        }
+        AddressType::Absolute => match instruction {
+            // the high byte is fetched after the return address is pushed
+            Instruct::JSR => 0,
+            // 5C of the 65C02 ignores the high byte and reads from the last page
+            Instruct::NOP if state.ir == 0x5C => 0xFF00 | (fetch_word(state, bus) & 0x00FF),
+            _ => fetch_word(state, bus),
+        },
+        AddressType::AbsoluteX | AddressType::AbsoluteY => {
+            let index = match mode {
+                AddressType::AbsoluteX => state.registers.xr,
+                _ => state.registers.yr,
+            };
+            let base = fetch_word(state, bus);
+            let addr = base.wrapping_add(index as u16);
+            unfixed = (base & 0xFF00) | (addr & 0x00FF);
+            if *extra_cycles > 0 && unfixed != addr {
+                cycles += 1;
+            }
+            addr
+        }
+        AddressType::Indirect => {
+            let ptr = fetch_word(state, bus);
+            read_word(state, bus, ptr, indirect_high(state, ptr))
+        }
+        AddressType::IndirectX => {
+            let base = fetch(state, bus);
+            let ptr = zero_page_index(state, base, state.registers.xr);
+            read_word(state, bus, ptr, pointer_high(state, ptr))
+        }
+        AddressType::ZeroPageIndirect => {
+            let ptr = fetch(state, bus) as u16;
+            read_word(state, bus, ptr, pointer_high(state, ptr))
+        }
+        AddressType::AbsoluteIndirectX => {
+            let ptr = fetch_word(state, bus).wrapping_add(state.registers.xr as u16);
+            read_word(state, bus, ptr, ptr.wrapping_add(1))
+        }
+        AddressType::IndirectY => {
+            let ptr = fetch(state, bus) as u16;
+            let base = read_word(state, bus, ptr, pointer_high(state, ptr));
+            let addr = base.wrapping_add(state.registers.yr as u16);
+            unfixed = (base & 0xFF00) | (addr & 0x00FF);
+            if *extra_cycles > 0 && unfixed != addr {
+                cycles += 1;
+            }
+            addr
+        }
+    };
+
+    match instruction {
+        Instruct::BRK => {
+            state.registers.pc = state.registers.pc.wrapping_add(1);
+            return interrupt(state, bus);
+        }
+        Instruct::JMP => state.registers.pc = addr,
+        Instruct::JSR => {
+            let low = fetch(state, bus) as u16;
+            let pc = state.registers.pc;
+            push(state, bus, (pc >> 8) as u8);
+            push(state, bus, pc as u8);
+            let high = fetch(state, bus) as u16;
+            state.registers.pc = low | (high << 8);
+        }
+        Instruct::RTS => {
+            let low = pull(state, bus) as u16;
+            let high = pull(state, bus) as u16;
+            state.registers.pc = (low | (high << 8)).wrapping_add(1);
+        }
+        Instruct::RTI => {
+            let sr = pull(state, bus);
+            state.registers.status_pull(sr);
+            let low = pull(state, bus) as u16;
+            let high = pull(state, bus) as u16;
+            state.registers.pc = low | (high << 8);
+        }
+        Instruct::PHA | Instruct::PHP | Instruct::PHX | Instruct::PHY => {
+            let value = match instruction {
+                Instruct::PHA => state.registers.ac,
+                Instruct::PHX => state.registers.xr,
+                Instruct::PHY => state.registers.yr,
+                _ => state.registers.status_push(true),
+            };
+            push(state, bus, value);
+        }
+        Instruct::PLA | Instruct::PLX | Instruct::PLY => {
+            state.pd = pull(state, bus);
+            let load = match instruction {
+                Instruct::PLA => Instruct::LDA,
+                Instruct::PLX => Instruct::LDX,
+                _ => Instruct::LDY,
+            };
+            execute(state, &load);
+        }
+        Instruct::PLP => {
+            let sr = pull(state, bus);
+            state.registers.status_pull(sr);
+        }
+        Instruct::STA
+        | Instruct::STX
+        | Instruct::STY
+        | Instruct::STZ
+        | Instruct::SAX
+        | Instruct::SHA
+        | Instruct::SHX
+        | Instruct::SHY
+        | Instruct::TAS => {
+            let registers = &state.registers;
+            let (addr, value) = match instruction {
+                Instruct::STA => (addr, registers.ac),
+                Instruct::STX => (addr, registers.xr),
+                Instruct::STY => (addr, registers.yr),
+                Instruct::STZ => (addr, 0),
+                Instruct::SAX => (addr, registers.ac & registers.xr),
+                _ => unstable_store(registers, instruction, unfixed, addr),
+            };
+            bus_write(state, bus, addr, value);
+            execute(state, instruction);
+        }
+        _ if matches!(mode, AddressType::Relative) => {
+            let offset = bus_read(state, bus, addr) as i8 as u16;
+            if branch_taken(&state.registers, instruction) {
+                let pc = state.registers.pc;
+                let target = pc.wrapping_add(offset);
+                cycles += if (target & 0xFF00) != (pc & 0xFF00) {
+                    2
+                } else {
+                    1
+                };
+                state.registers.pc = target;
+            }
+        }
+        _ if matches!(mode, AddressType::Accumulator) => {
+            let value = state.registers.ac;
+            state.registers.ac = modify(&mut state.registers, state.variant, instruction, value);
+        }
+        _ if is_rmw(instruction) => {
+            let value = bus_read(state, bus, addr);
+            let result = modify(&mut state.registers, state.variant, instruction, value);
+            bus_write(state, bus, addr, result);
+        }
+        _ if matches!(mode, AddressType::Impl) => execute(state, instruction),
+        _ => {
+            state.pd = bus_read(state, bus, addr);
+            execute(state, instruction);
+        }
+    };
+    if decimal_cycle(state, instruction) {
+        cycles += 1;
+    }
+    cycles
+}
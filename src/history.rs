@@ -0,0 +1,152 @@
+// Reverse execution for the cycle level core.
+//
+// Every half cycle run through `History` keeps the state from before it and the old value
+// of every byte it wrote, so going back restores the state and undoes the writes in
+// reverse order instead of keeping copies of the whole memory.
+
+use std::collections::VecDeque;
+
+use crate::m6502::{half_step, instruction_done, State};
+use crate::memory::{Bus, Memory};
+
+struct Entry {
+    state: State,
+    // address and previous value of every write
+    writes: Vec<(u16, u8)>,
+}
+
+// passes the bus cycles through to the memory, remembering what the writes replaced
+struct Journal<'a, M: Memory + ?Sized> {
+    memory: &'a mut M,
+    writes: Vec<(u16, u8)>,
+}
+
+impl<M: Memory + ?Sized> Bus for Journal<'_, M> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.memory.get(addr)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.writes.push((addr, self.memory.get(addr)));
+        self.memory.set(addr, value);
+    }
+}
+
+pub struct History {
+    entries: VecDeque<Entry>,
+    // the most half cycles kept, the oldest are dropped first
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    // half cycles that can be undone
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn half_step<M: Memory + ?Sized>(&mut self, state: &mut State, memory: &mut M) {
+        let before = state.clone();
+        let mut journal = Journal {
+            memory,
+            writes: Vec::new(),
+        };
+        half_step(state, &mut journal);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            state: before,
+            writes: journal.writes,
+        });
+    }
+
+    pub fn cycle<M: Memory + ?Sized>(&mut self, state: &mut State, memory: &mut M) {
+        self.half_step(state, memory);
+        if state.clock2 {
+            self.half_step(state, memory);
+        }
+    }
+
+    // runs until the current instruction is done, returns the cycles taken
+    pub fn run_instruction<M: Memory + ?Sized>(
+        &mut self,
+        state: &mut State,
+        memory: &mut M,
+    ) -> u32 {
+        let mut cycles = 0;
+        loop {
+            self.cycle(state, memory);
+            cycles += 1;
+            if instruction_done(state) {
+                return cycles;
+            }
+        }
+    }
+
+    // undoes the last half cycle, returns false when there is nothing left to undo
+    pub fn half_step_back<M: Memory + ?Sized>(
+        &mut self,
+        state: &mut State,
+        memory: &mut M,
+    ) -> bool {
+        let Some(entry) = self.entries.pop_back() else {
+            return false;
+        };
+        for (addr, value) in entry.writes.into_iter().rev() {
+            memory.set(addr, value);
+        }
+        *state = entry.state;
+        true
+    }
+
+    // goes back to the start of the current or previous cycle, returns the half cycles undone
+    pub fn cycle_back<M: Memory + ?Sized>(&mut self, state: &mut State, memory: &mut M) -> usize {
+        self.back_until(state, memory, |state| state.clock1)
+    }
+
+    // goes back to the start of the current or previous instruction, returns the half cycles
+    // undone
+    pub fn instruction_back<M: Memory + ?Sized>(
+        &mut self,
+        state: &mut State,
+        memory: &mut M,
+    ) -> usize {
+        self.back_until(state, memory, |state| {
+            state.clock1 && state.next_timing.is_fetch()
+        })
+    }
+
+    fn back_until<M: Memory + ?Sized>(
+        &mut self,
+        state: &mut State,
+        memory: &mut M,
+        done: impl Fn(&State) -> bool,
+    ) -> usize {
+        let mut undone = 0;
+        while self.half_step_back(state, memory) {
+            undone += 1;
+            if done(state) {
+                break;
+            }
+        }
+        undone
+    }
+}
@@ -0,0 +1,237 @@
+// Recording and replay of everything that reaches the machine from the outside.
+//
+// Each event is stamped with the half cycle of the clock it happened before, replaying the
+// events at the same points of a cycle level run from the same starting machine gives the
+// same execution. The clock keeps running while the processor is held in reset, jammed or
+// waiting, so every event gets its own point in time. The file format is plain text with
+// one event per line, the cycle of the clock and the half of it:
+//
+//     # rs6502 input recording 2
+//     120 φ1 irq 1
+//     4312 φ2 write 00ff 77
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::m6502::{half_step, State};
+use crate::memory::Memory;
+
+const HEADER: &str = "# rs6502 input recording 2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Res(bool),
+    Irq(bool),
+    Nmi(bool),
+    Rdy(bool),
+    So(bool),
+    // a byte put into memory by a device, like a key press or a random number
+    Write(u16, u8),
+}
+
+impl Input {
+    pub fn apply(&self, state: &mut State, memory: &mut dyn Memory) {
+        match *self {
+            Input::Res(level) => state.res = level,
+            Input::Irq(level) => state.irq = level,
+            Input::Nmi(level) => state.nmi = level,
+            Input::Rdy(level) => state.rdy = level,
+            Input::So(level) => state.so = level,
+            Input::Write(addr, value) => memory.set(addr, value),
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Input::Res(level) => write!(f, "res {}", *level as u8),
+            Input::Irq(level) => write!(f, "irq {}", *level as u8),
+            Input::Nmi(level) => write!(f, "nmi {}", *level as u8),
+            Input::Rdy(level) => write!(f, "rdy {}", *level as u8),
+            Input::So(level) => write!(f, "so {}", *level as u8),
+            Input::Write(addr, value) => write!(f, "write {addr:04x} {value:02x}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    // the value of `State::half_cycles` when the input arrived
+    pub half_cycle: u64,
+    pub input: Input,
+}
+
+impl Event {
+    fn is_due(&self, state: &State) -> bool {
+        self.half_cycle == state.half_cycles
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // an odd count is between φ1 and φ2 of a cycle
+        let cycle = self.half_cycle.div_ceil(2);
+        let phase = if self.half_cycle % 2 == 1 {
+            "φ2"
+        } else {
+            "φ1"
+        };
+        write!(f, "{cycle} {phase} {}", self.input)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub events: Vec<Event>,
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording { events: Vec::new() }
+    }
+
+    // applies the input and records it at the current point of the machine
+    pub fn input(&mut self, state: &mut State, memory: &mut dyn Memory, input: Input) {
+        input.apply(state, memory);
+        self.events.push(Event {
+            half_cycle: state.half_cycles,
+            input,
+        });
+    }
+
+    // forgets the events after the current point, used after stepping backwards
+    pub fn rewind(&mut self, state: &State) {
+        while self
+            .events
+            .last()
+            .is_some_and(|event| event.half_cycle > state.half_cycles)
+        {
+            self.events.pop();
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Recording> {
+        Recording::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Recording> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some(HEADER) {
+            return Err(invalid(1, "not an input recording"));
+        }
+        let mut events = Vec::new();
+        for (i, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let event = parse_event(line).ok_or_else(|| invalid(i + 1, line))?;
+            events.push(event);
+        }
+        Ok(Recording { events })
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        for event in &self.events {
+            writeln!(f, "{event}")?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(line: usize, reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {reason}"))
+}
+
+fn parse_event(line: &str) -> Option<Event> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let [cycle, phase, name, args @ ..] = words.as_slice() else {
+        return None;
+    };
+    let cycle: u64 = cycle.parse().ok()?;
+    let half_cycle = match *phase {
+        "φ1" => cycle.checked_mul(2)?,
+        "φ2" => cycle.checked_mul(2)?.checked_sub(1)?,
+        _ => return None,
+    };
+    let level = || match args {
+        ["0"] => Some(false),
+        ["1"] => Some(true),
+        _ => None,
+    };
+    let input = match *name {
+        "res" => Input::Res(level()?),
+        "irq" => Input::Irq(level()?),
+        "nmi" => Input::Nmi(level()?),
+        "rdy" => Input::Rdy(level()?),
+        "so" => Input::So(level()?),
+        "write" => match args {
+            [addr, value] => Input::Write(
+                u16::from_str_radix(addr, 16).ok()?,
+                u8::from_str_radix(value, 16).ok()?,
+            ),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(Event { half_cycle, input })
+}
+
+// feeds a recording back into a cycle level run
+pub struct Replay<'a> {
+    events: &'a [Event],
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(recording: &'a Recording) -> Replay<'a> {
+        Replay {
+            events: &recording.events,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // skips the events from before the current point of the machine, to go on with a replay
+    // that was left part way through
+    pub fn seek(&mut self, state: &State) {
+        let start = self
+            .events
+            .partition_point(|event| event.half_cycle < state.half_cycles);
+        self.events = &self.events[start..];
+    }
+
+    // applies the events recorded at the current point of the machine
+    pub fn apply(&mut self, state: &mut State, memory: &mut dyn Memory) {
+        while let Some((event, rest)) = self.events.split_first() {
+            if !event.is_due(state) {
+                break;
+            }
+            event.input.apply(state, memory);
+            self.events = rest;
+        }
+    }
+
+    pub fn half_step<M: Memory>(&mut self, state: &mut State, memory: &mut M) {
+        self.apply(state, memory);
+        half_step(state, memory);
+    }
+
+    pub fn cycle<M: Memory>(&mut self, state: &mut State, memory: &mut M) {
+        self.half_step(state, memory);
+        if state.clock2 {
+            self.half_step(state, memory);
+        }
+    }
+}
@@ -1,8 +1,8 @@
-#[allow(clippy::upper_case_acronyms)]
+#![allow(clippy::upper_case_acronyms)]
 
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Instruct {
     ADC, // add with carry
     AND, // and (with accumulator)
@@ -60,9 +60,42 @@ pub enum Instruct {
     TXA, // transfer X to accumulator
     TXS, // transfer X to stack pointer
     TYA, // transfer Y to accumulator
+
+    // 65C02 instructions
+    BRA, // branch always
+    PHX, // push X
+    PHY, // push Y
+    PLX, // pull X
+    PLY, // pull Y
+    STP, // stop the clock until reset
+    STZ, // store zero
+    TRB, // test and reset bits
+    TSB, // test and set bits
+    WAI, // wait for interrupt
+
+    // undocumented NMOS instructions
+    ALR, // and immediate then shift right accumulator
+    ANC, // and immediate, copy bit 7 to carry
+    ANE, // and X and immediate with unstable accumulator (XAA)
+    ARR, // and immediate then rotate right accumulator
+    DCP, // decrement then compare with accumulator
+    ISC, // increment then subtract with carry
+    LAS, // and stack pointer, load accumulator, X and stack pointer
+    LAX, // load accumulator and X
+    LXA, // and immediate with unstable accumulator, load accumulator and X
+    RLA, // rotate left then and with accumulator
+    RRA, // rotate right then add with carry
+    SAX, // store accumulator and X
+    SBX, // X and accumulator minus immediate into X
+    SHA, // store accumulator and X and high address byte + 1
+    SHX, // store X and high address byte + 1
+    SHY, // store Y and high address byte + 1
+    SLO, // shift left then or with accumulator
+    SRE, // shift right then exclusive or with accumulator
+    TAS, // transfer accumulator and X to stack pointer, store it and high address byte + 1
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AddressType {
     // OPC
     Impl,
@@ -90,6 +123,10 @@ pub enum AddressType {
     IndirectX,
     // <op> (<8>),Y
     IndirectY,
+    // <op> (<8>), 65C02 only
+    ZeroPageIndirect,
+    // <op> (<16>,X), 65C02 only
+    AbsoluteIndirectX,
 }
 
 pub struct InstructionInfo {
@@ -100,6 +137,7 @@ pub struct InstructionInfo {
 }
 
 impl Instruct {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(text: &str) -> Option<Instruct> {
         match text.to_uppercase().as_str() {
             "ADC" => Some(Instruct::ADC),
@@ -158,6 +196,35 @@ impl Instruct {
             "TXA" => Some(Instruct::TXA),
             "TXS" => Some(Instruct::TXS),
             "TYA" => Some(Instruct::TYA),
+            "BRA" => Some(Instruct::BRA),
+            "PHX" => Some(Instruct::PHX),
+            "PHY" => Some(Instruct::PHY),
+            "PLX" => Some(Instruct::PLX),
+            "PLY" => Some(Instruct::PLY),
+            "STP" => Some(Instruct::STP),
+            "STZ" => Some(Instruct::STZ),
+            "TRB" => Some(Instruct::TRB),
+            "TSB" => Some(Instruct::TSB),
+            "WAI" => Some(Instruct::WAI),
+            "ALR" | "ASR" => Some(Instruct::ALR),
+            "ANC" => Some(Instruct::ANC),
+            "ANE" | "XAA" => Some(Instruct::ANE),
+            "ARR" => Some(Instruct::ARR),
+            "DCP" => Some(Instruct::DCP),
+            "ISC" | "ISB" => Some(Instruct::ISC),
+            "LAS" | "LAR" => Some(Instruct::LAS),
+            "LAX" => Some(Instruct::LAX),
+            "LXA" => Some(Instruct::LXA),
+            "RLA" => Some(Instruct::RLA),
+            "RRA" => Some(Instruct::RRA),
+            "SAX" => Some(Instruct::SAX),
+            "SBX" | "AXS" => Some(Instruct::SBX),
+            "SHA" | "AHX" => Some(Instruct::SHA),
+            "SHX" => Some(Instruct::SHX),
+            "SHY" => Some(Instruct::SHY),
+            "SLO" => Some(Instruct::SLO),
+            "SRE" => Some(Instruct::SRE),
+            "TAS" | "SHS" => Some(Instruct::TAS),
             _ => None,
         }
     }
@@ -220,6 +287,35 @@ impl Instruct {
             Instruct::TXA => "TXA",
             Instruct::TXS => "TXS",
             Instruct::TYA => "TYA",
+            Instruct::BRA => "BRA",
+            Instruct::PHX => "PHX",
+            Instruct::PHY => "PHY",
+            Instruct::PLX => "PLX",
+            Instruct::PLY => "PLY",
+            Instruct::STP => "STP",
+            Instruct::STZ => "STZ",
+            Instruct::TRB => "TRB",
+            Instruct::TSB => "TSB",
+            Instruct::WAI => "WAI",
+            Instruct::ALR => "ALR",
+            Instruct::ANC => "ANC",
+            Instruct::ANE => "ANE",
+            Instruct::ARR => "ARR",
+            Instruct::DCP => "DCP",
+            Instruct::ISC => "ISC",
+            Instruct::LAS => "LAS",
+            Instruct::LAX => "LAX",
+            Instruct::LXA => "LXA",
+            Instruct::RLA => "RLA",
+            Instruct::RRA => "RRA",
+            Instruct::SAX => "SAX",
+            Instruct::SBX => "SBX",
+            Instruct::SHA => "SHA",
+            Instruct::SHX => "SHX",
+            Instruct::SHY => "SHY",
+            Instruct::SLO => "SLO",
+            Instruct::SRE => "SRE",
+            Instruct::TAS => "TAS",
         }
     }
 
@@ -422,6 +518,11 @@ impl Instruct {
             },
             Instruct::NOP => match addr {
                 AddressType::Impl => Some(0xEA),
+                AddressType::Immediate => Some(0x80),
+                AddressType::ZeroPage => Some(0x4),
+                AddressType::ZeroPageX => Some(0x14),
+                AddressType::Absolute => Some(0xC),
+                AddressType::AbsoluteX => Some(0x1C),
                 _ => None,
             },
             Instruct::ORA => match addr {
@@ -544,6 +645,138 @@ impl Instruct {
                 AddressType::Impl => Some(0x98),
                 _ => None,
             },
+            // only exist on the 65C02, see `Variant::op_code`
+            Instruct::BRA
+            | Instruct::PHX
+            | Instruct::PHY
+            | Instruct::PLX
+            | Instruct::PLY
+            | Instruct::STP
+            | Instruct::STZ
+            | Instruct::TRB
+            | Instruct::TSB
+            | Instruct::WAI => None,
+            Instruct::ALR => match addr {
+                AddressType::Immediate => Some(0x4B),
+                _ => None,
+            },
+            Instruct::ANC => match addr {
+                AddressType::Immediate => Some(0xB),
+                _ => None,
+            },
+            Instruct::ANE => match addr {
+                AddressType::Immediate => Some(0x8B),
+                _ => None,
+            },
+            Instruct::ARR => match addr {
+                AddressType::Immediate => Some(0x6B),
+                _ => None,
+            },
+            Instruct::DCP => match addr {
+                AddressType::ZeroPage => Some(0xC7),
+                AddressType::ZeroPageX => Some(0xD7),
+                AddressType::Absolute => Some(0xCF),
+                AddressType::AbsoluteX => Some(0xDF),
+                AddressType::AbsoluteY => Some(0xDB),
+                AddressType::IndirectX => Some(0xC3),
+                AddressType::IndirectY => Some(0xD3),
+                _ => None,
+            },
+            Instruct::ISC => match addr {
+                AddressType::ZeroPage => Some(0xE7),
+                AddressType::ZeroPageX => Some(0xF7),
+                AddressType::Absolute => Some(0xEF),
+                AddressType::AbsoluteX => Some(0xFF),
+                AddressType::AbsoluteY => Some(0xFB),
+                AddressType::IndirectX => Some(0xE3),
+                AddressType::IndirectY => Some(0xF3),
+                _ => None,
+            },
+            Instruct::LAS => match addr {
+                AddressType::AbsoluteY => Some(0xBB),
+                _ => None,
+            },
+            Instruct::LAX => match addr {
+                AddressType::ZeroPage => Some(0xA7),
+                AddressType::ZeroPageY => Some(0xB7),
+                AddressType::Absolute => Some(0xAF),
+                AddressType::AbsoluteY => Some(0xBF),
+                AddressType::IndirectX => Some(0xA3),
+                AddressType::IndirectY => Some(0xB3),
+                _ => None,
+            },
+            Instruct::LXA => match addr {
+                AddressType::Immediate => Some(0xAB),
+                _ => None,
+            },
+            Instruct::RLA => match addr {
+                AddressType::ZeroPage => Some(0x27),
+                AddressType::ZeroPageX => Some(0x37),
+                AddressType::Absolute => Some(0x2F),
+                AddressType::AbsoluteX => Some(0x3F),
+                AddressType::AbsoluteY => Some(0x3B),
+                AddressType::IndirectX => Some(0x23),
+                AddressType::IndirectY => Some(0x33),
+                _ => None,
+            },
+            Instruct::RRA => match addr {
+                AddressType::ZeroPage => Some(0x67),
+                AddressType::ZeroPageX => Some(0x77),
+                AddressType::Absolute => Some(0x6F),
+                AddressType::AbsoluteX => Some(0x7F),
+                AddressType::AbsoluteY => Some(0x7B),
+                AddressType::IndirectX => Some(0x63),
+                AddressType::IndirectY => Some(0x73),
+                _ => None,
+            },
+            Instruct::SAX => match addr {
+                AddressType::ZeroPage => Some(0x87),
+                AddressType::ZeroPageY => Some(0x97),
+                AddressType::Absolute => Some(0x8F),
+                AddressType::IndirectX => Some(0x83),
+                _ => None,
+            },
+            Instruct::SBX => match addr {
+                AddressType::Immediate => Some(0xCB),
+                _ => None,
+            },
+            Instruct::SHA => match addr {
+                AddressType::AbsoluteY => Some(0x9F),
+                AddressType::IndirectY => Some(0x93),
+                _ => None,
+            },
+            Instruct::SHX => match addr {
+                AddressType::AbsoluteY => Some(0x9E),
+                _ => None,
+            },
+            Instruct::SHY => match addr {
+                AddressType::AbsoluteX => Some(0x9C),
+                _ => None,
+            },
+            Instruct::SLO => match addr {
+                AddressType::ZeroPage => Some(0x7),
+                AddressType::ZeroPageX => Some(0x17),
+                AddressType::Absolute => Some(0xF),
+                AddressType::AbsoluteX => Some(0x1F),
+                AddressType::AbsoluteY => Some(0x1B),
+                AddressType::IndirectX => Some(0x3),
+                AddressType::IndirectY => Some(0x13),
+                _ => None,
+            },
+            Instruct::SRE => match addr {
+                AddressType::ZeroPage => Some(0x47),
+                AddressType::ZeroPageX => Some(0x57),
+                AddressType::Absolute => Some(0x4F),
+                AddressType::AbsoluteX => Some(0x5F),
+                AddressType::AbsoluteY => Some(0x5B),
+                AddressType::IndirectX => Some(0x43),
+                AddressType::IndirectY => Some(0x53),
+                _ => None,
+            },
+            Instruct::TAS => match addr {
+                AddressType::AbsoluteY => Some(0x9B),
+                _ => None,
+            },
         }
     }
 
@@ -1455,12 +1688,1362 @@ impl Instruct {
                 cycles: 7,
                 extra_cycles: 0,
             }),
-            _ => None,
+            _ => Self::from_undocumented_op_code(op_code),
         }
     }
-}
 
-impl fmt::Display for Instruct {
+    // the opcodes that are new or behave differently on the 65C02, every unused opcode is a NOP
+    fn from_cmos_op_code(op_code: u8) -> Option<&'static InstructionInfo> {
+        match op_code {
+            0x2 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x3 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x4 => Some(&InstructionInfo {
+                instruction: Instruct::TSB,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x7 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xB => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xC => Some(&InstructionInfo {
+                instruction: Instruct::TSB,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0xF => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x12 => Some(&InstructionInfo {
+                instruction: Instruct::ORA,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x13 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x14 => Some(&InstructionInfo {
+                instruction: Instruct::TRB,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x17 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x1A => Some(&InstructionInfo {
+                instruction: Instruct::INC,
+                mode: AddressType::Accumulator,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x1B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x1C => Some(&InstructionInfo {
+                instruction: Instruct::TRB,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x1E => Some(&InstructionInfo {
+                instruction: Instruct::ASL,
+                mode: AddressType::AbsoluteX,
+                cycles: 6,
+                extra_cycles: 1,
+            }),
+            0x1F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x22 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x23 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x27 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x2B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x2F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x32 => Some(&InstructionInfo {
+                instruction: Instruct::AND,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x33 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x34 => Some(&InstructionInfo {
+                instruction: Instruct::BIT,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x37 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x3A => Some(&InstructionInfo {
+                instruction: Instruct::DEC,
+                mode: AddressType::Accumulator,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x3B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x3C => Some(&InstructionInfo {
+                instruction: Instruct::BIT,
+                mode: AddressType::AbsoluteX,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0x3E => Some(&InstructionInfo {
+                instruction: Instruct::ROL,
+                mode: AddressType::AbsoluteX,
+                cycles: 6,
+                extra_cycles: 1,
+            }),
+            0x3F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x42 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x43 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x44 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPage,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0x47 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x4B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x4F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x52 => Some(&InstructionInfo {
+                instruction: Instruct::EOR,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x53 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x54 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x57 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x5A => Some(&InstructionInfo {
+                instruction: Instruct::PHY,
+                mode: AddressType::Impl,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0x5B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x5C => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Absolute,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x5E => Some(&InstructionInfo {
+                instruction: Instruct::LSR,
+                mode: AddressType::AbsoluteX,
+                cycles: 6,
+                extra_cycles: 1,
+            }),
+            0x5F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x62 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x63 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x64 => Some(&InstructionInfo {
+                instruction: Instruct::STZ,
+                mode: AddressType::ZeroPage,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0x67 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x6B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x6C => Some(&InstructionInfo {
+                instruction: Instruct::JMP,
+                mode: AddressType::Indirect,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x6F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x72 => Some(&InstructionInfo {
+                instruction: Instruct::ADC,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x73 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x74 => Some(&InstructionInfo {
+                instruction: Instruct::STZ,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x77 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x7A => Some(&InstructionInfo {
+                instruction: Instruct::PLY,
+                mode: AddressType::Impl,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x7B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x7C => Some(&InstructionInfo {
+                instruction: Instruct::JMP,
+                mode: AddressType::AbsoluteIndirectX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x7E => Some(&InstructionInfo {
+                instruction: Instruct::ROR,
+                mode: AddressType::AbsoluteX,
+                cycles: 6,
+                extra_cycles: 1,
+            }),
+            0x7F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x80 => Some(&InstructionInfo {
+                instruction: Instruct::BRA,
+                mode: AddressType::Relative,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x82 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x83 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x87 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x89 => Some(&InstructionInfo {
+                instruction: Instruct::BIT,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x8B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x8F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x92 => Some(&InstructionInfo {
+                instruction: Instruct::STA,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x93 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x97 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x9B => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0x9C => Some(&InstructionInfo {
+                instruction: Instruct::STZ,
+                mode: AddressType::Absolute,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x9E => Some(&InstructionInfo {
+                instruction: Instruct::STZ,
+                mode: AddressType::AbsoluteX,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x9F => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xA3 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xA7 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xAB => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xAF => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xB2 => Some(&InstructionInfo {
+                instruction: Instruct::LDA,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0xB3 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xB7 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xBB => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xBF => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xC2 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xC3 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xC7 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xCB => Some(&InstructionInfo {
+                instruction: Instruct::WAI,
+                mode: AddressType::Impl,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0xCF => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xD2 => Some(&InstructionInfo {
+                instruction: Instruct::CMP,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0xD3 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xD4 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xD7 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xDA => Some(&InstructionInfo {
+                instruction: Instruct::PHX,
+                mode: AddressType::Impl,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0xDB => Some(&InstructionInfo {
+                instruction: Instruct::STP,
+                mode: AddressType::Impl,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0xDC => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Absolute,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xDF => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xE2 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xE3 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xE7 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xEB => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xEF => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xF2 => Some(&InstructionInfo {
+                instruction: Instruct::SBC,
+                mode: AddressType::ZeroPageIndirect,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0xF3 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xF4 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xF7 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xFA => Some(&InstructionInfo {
+                instruction: Instruct::PLX,
+                mode: AddressType::Impl,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xFB => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            0xFC => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Absolute,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xFF => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 1,
+                extra_cycles: 0,
+            }),
+            _ => None,
+        }
+    }
+
+    // true for the opcodes missing from the data sheet, the JAM opcodes are never decoded
+    pub fn is_undocumented(op_code: u8) -> bool {
+        Self::from_undocumented_op_code(op_code).is_some()
+    }
+
+    fn from_undocumented_op_code(op_code: u8) -> Option<&'static InstructionInfo> {
+        match op_code {
+            0x3 => Some(&InstructionInfo {
+                instruction: Instruct::SLO,
+                mode: AddressType::IndirectX,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x4 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPage,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0x7 => Some(&InstructionInfo {
+                instruction: Instruct::SLO,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0xB => Some(&InstructionInfo {
+                instruction: Instruct::ANC,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xC => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Absolute,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xF => Some(&InstructionInfo {
+                instruction: Instruct::SLO,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x13 => Some(&InstructionInfo {
+                instruction: Instruct::SLO,
+                mode: AddressType::IndirectY,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x14 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x17 => Some(&InstructionInfo {
+                instruction: Instruct::SLO,
+                mode: AddressType::ZeroPageX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x1A => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x1B => Some(&InstructionInfo {
+                instruction: Instruct::SLO,
+                mode: AddressType::AbsoluteY,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x1C => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::AbsoluteX,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0x1F => Some(&InstructionInfo {
+                instruction: Instruct::SLO,
+                mode: AddressType::AbsoluteX,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x23 => Some(&InstructionInfo {
+                instruction: Instruct::RLA,
+                mode: AddressType::IndirectX,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x27 => Some(&InstructionInfo {
+                instruction: Instruct::RLA,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x2B => Some(&InstructionInfo {
+                instruction: Instruct::ANC,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x2F => Some(&InstructionInfo {
+                instruction: Instruct::RLA,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x33 => Some(&InstructionInfo {
+                instruction: Instruct::RLA,
+                mode: AddressType::IndirectY,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x34 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x37 => Some(&InstructionInfo {
+                instruction: Instruct::RLA,
+                mode: AddressType::ZeroPageX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x3A => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x3B => Some(&InstructionInfo {
+                instruction: Instruct::RLA,
+                mode: AddressType::AbsoluteY,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x3C => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::AbsoluteX,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0x3F => Some(&InstructionInfo {
+                instruction: Instruct::RLA,
+                mode: AddressType::AbsoluteX,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x43 => Some(&InstructionInfo {
+                instruction: Instruct::SRE,
+                mode: AddressType::IndirectX,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x44 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPage,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0x47 => Some(&InstructionInfo {
+                instruction: Instruct::SRE,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x4B => Some(&InstructionInfo {
+                instruction: Instruct::ALR,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x4F => Some(&InstructionInfo {
+                instruction: Instruct::SRE,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x53 => Some(&InstructionInfo {
+                instruction: Instruct::SRE,
+                mode: AddressType::IndirectY,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x54 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x57 => Some(&InstructionInfo {
+                instruction: Instruct::SRE,
+                mode: AddressType::ZeroPageX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x5A => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x5B => Some(&InstructionInfo {
+                instruction: Instruct::SRE,
+                mode: AddressType::AbsoluteY,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x5C => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::AbsoluteX,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0x5F => Some(&InstructionInfo {
+                instruction: Instruct::SRE,
+                mode: AddressType::AbsoluteX,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x63 => Some(&InstructionInfo {
+                instruction: Instruct::RRA,
+                mode: AddressType::IndirectX,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x64 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPage,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0x67 => Some(&InstructionInfo {
+                instruction: Instruct::RRA,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x6B => Some(&InstructionInfo {
+                instruction: Instruct::ARR,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x6F => Some(&InstructionInfo {
+                instruction: Instruct::RRA,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x73 => Some(&InstructionInfo {
+                instruction: Instruct::RRA,
+                mode: AddressType::IndirectY,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0x74 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x77 => Some(&InstructionInfo {
+                instruction: Instruct::RRA,
+                mode: AddressType::ZeroPageX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x7A => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x7B => Some(&InstructionInfo {
+                instruction: Instruct::RRA,
+                mode: AddressType::AbsoluteY,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x7C => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::AbsoluteX,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0x7F => Some(&InstructionInfo {
+                instruction: Instruct::RRA,
+                mode: AddressType::AbsoluteX,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0x80 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x82 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x83 => Some(&InstructionInfo {
+                instruction: Instruct::SAX,
+                mode: AddressType::IndirectX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x87 => Some(&InstructionInfo {
+                instruction: Instruct::SAX,
+                mode: AddressType::ZeroPage,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0x89 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x8B => Some(&InstructionInfo {
+                instruction: Instruct::ANE,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0x8F => Some(&InstructionInfo {
+                instruction: Instruct::SAX,
+                mode: AddressType::Absolute,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x93 => Some(&InstructionInfo {
+                instruction: Instruct::SHA,
+                mode: AddressType::IndirectY,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0x97 => Some(&InstructionInfo {
+                instruction: Instruct::SAX,
+                mode: AddressType::ZeroPageY,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0x9B => Some(&InstructionInfo {
+                instruction: Instruct::TAS,
+                mode: AddressType::AbsoluteY,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x9C => Some(&InstructionInfo {
+                instruction: Instruct::SHY,
+                mode: AddressType::AbsoluteX,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x9E => Some(&InstructionInfo {
+                instruction: Instruct::SHX,
+                mode: AddressType::AbsoluteY,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0x9F => Some(&InstructionInfo {
+                instruction: Instruct::SHA,
+                mode: AddressType::AbsoluteY,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0xA3 => Some(&InstructionInfo {
+                instruction: Instruct::LAX,
+                mode: AddressType::IndirectX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0xA7 => Some(&InstructionInfo {
+                instruction: Instruct::LAX,
+                mode: AddressType::ZeroPage,
+                cycles: 3,
+                extra_cycles: 0,
+            }),
+            0xAB => Some(&InstructionInfo {
+                instruction: Instruct::LXA,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xAF => Some(&InstructionInfo {
+                instruction: Instruct::LAX,
+                mode: AddressType::Absolute,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xB3 => Some(&InstructionInfo {
+                instruction: Instruct::LAX,
+                mode: AddressType::IndirectY,
+                cycles: 5,
+                extra_cycles: 1,
+            }),
+            0xB7 => Some(&InstructionInfo {
+                instruction: Instruct::LAX,
+                mode: AddressType::ZeroPageY,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xBB => Some(&InstructionInfo {
+                instruction: Instruct::LAS,
+                mode: AddressType::AbsoluteY,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0xBF => Some(&InstructionInfo {
+                instruction: Instruct::LAX,
+                mode: AddressType::AbsoluteY,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0xC2 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xC3 => Some(&InstructionInfo {
+                instruction: Instruct::DCP,
+                mode: AddressType::IndirectX,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0xC7 => Some(&InstructionInfo {
+                instruction: Instruct::DCP,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0xCB => Some(&InstructionInfo {
+                instruction: Instruct::SBX,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xCF => Some(&InstructionInfo {
+                instruction: Instruct::DCP,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0xD3 => Some(&InstructionInfo {
+                instruction: Instruct::DCP,
+                mode: AddressType::IndirectY,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0xD4 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xD7 => Some(&InstructionInfo {
+                instruction: Instruct::DCP,
+                mode: AddressType::ZeroPageX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0xDA => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xDB => Some(&InstructionInfo {
+                instruction: Instruct::DCP,
+                mode: AddressType::AbsoluteY,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0xDC => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::AbsoluteX,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0xDF => Some(&InstructionInfo {
+                instruction: Instruct::DCP,
+                mode: AddressType::AbsoluteX,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0xE2 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xE3 => Some(&InstructionInfo {
+                instruction: Instruct::ISC,
+                mode: AddressType::IndirectX,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0xE7 => Some(&InstructionInfo {
+                instruction: Instruct::ISC,
+                mode: AddressType::ZeroPage,
+                cycles: 5,
+                extra_cycles: 0,
+            }),
+            0xEB => Some(&InstructionInfo {
+                instruction: Instruct::SBC,
+                mode: AddressType::Immediate,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xEF => Some(&InstructionInfo {
+                instruction: Instruct::ISC,
+                mode: AddressType::Absolute,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0xF3 => Some(&InstructionInfo {
+                instruction: Instruct::ISC,
+                mode: AddressType::IndirectY,
+                cycles: 8,
+                extra_cycles: 0,
+            }),
+            0xF4 => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::ZeroPageX,
+                cycles: 4,
+                extra_cycles: 0,
+            }),
+            0xF7 => Some(&InstructionInfo {
+                instruction: Instruct::ISC,
+                mode: AddressType::ZeroPageX,
+                cycles: 6,
+                extra_cycles: 0,
+            }),
+            0xFA => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::Impl,
+                cycles: 2,
+                extra_cycles: 0,
+            }),
+            0xFB => Some(&InstructionInfo {
+                instruction: Instruct::ISC,
+                mode: AddressType::AbsoluteY,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            0xFC => Some(&InstructionInfo {
+                instruction: Instruct::NOP,
+                mode: AddressType::AbsoluteX,
+                cycles: 4,
+                extra_cycles: 1,
+            }),
+            0xFF => Some(&InstructionInfo {
+                instruction: Instruct::ISC,
+                mode: AddressType::AbsoluteX,
+                cycles: 7,
+                extra_cycles: 0,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+// the processors that can be emulated, they share the NMOS core with a few differences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    // the original MOS 6502
+    #[default]
+    Nmos6502,
+    // the CMOS 65C02 with WAI and STP, the Rockwell bit instructions are NOPs.
+    // ADC and SBC take an extra cycle in decimal mode
+    Cmos65C02,
+    // the NES processor, decimal mode is disconnected
+    Ricoh2A03,
+    // the C64 processor, with an I/O port at $00 and $01
+    Mos6510,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [
+        Variant::Nmos6502,
+        Variant::Cmos65C02,
+        Variant::Ricoh2A03,
+        Variant::Mos6510,
+    ];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(text: &str) -> Option<Variant> {
+        match text.to_uppercase().as_str() {
+            "6502" | "NMOS" => Some(Variant::Nmos6502),
+            "65C02" | "CMOS" => Some(Variant::Cmos65C02),
+            "2A03" => Some(Variant::Ricoh2A03),
+            "6510" => Some(Variant::Mos6510),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Variant::Nmos6502 => "6502",
+            Variant::Cmos65C02 => "65C02",
+            Variant::Ricoh2A03 => "2A03",
+            Variant::Mos6510 => "6510",
+        }
+    }
+
+    pub fn is_cmos(&self) -> bool {
+        *self == Variant::Cmos65C02
+    }
+
+    pub fn has_decimal(&self) -> bool {
+        *self != Variant::Ricoh2A03
+    }
+
+    // the NMOS parts decode the undocumented opcodes too, the core decides if they run
+    pub fn decode(&self, op_code: u8) -> Option<&'static InstructionInfo> {
+        if !self.is_cmos() {
+            return Instruct::from_op_code(op_code);
+        }
+        Instruct::from_cmos_op_code(op_code).or_else(|| {
+            Instruct::from_op_code(op_code).filter(|_| !Instruct::is_undocumented(op_code))
+        })
+    }
+
+    pub fn op_code(&self, instruct: &Instruct, addr: &AddressType) -> Option<u8> {
+        if !self.is_cmos() {
+            return instruct.get_op_code(addr);
+        }
+        // the NOPs filling the unused opcodes can't be assembled
+        if *instruct != Instruct::NOP {
+            let op_code = (0..=0xFF).find(|op_code| {
+                Instruct::from_cmos_op_code(*op_code)
+                    .is_some_and(|info| info.instruction == *instruct && info.mode == *addr)
+            });
+            if op_code.is_some() {
+                return op_code;
+            }
+        }
+        instruct
+            .get_op_code(addr)
+            .filter(|op_code| !Instruct::is_undocumented(*op_code))
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        Quirks {
+            indirect_jump_wrap: !self.is_cmos(),
+            zero_page_wrap: true,
+            pointer_wrap: true,
+        }
+    }
+
+    // true if the instruction exists in any addressing mode
+    pub fn has(&self, instruct: &Instruct) -> bool {
+        (0..=0xFF).any(|op_code| {
+            self.decode(op_code)
+                .is_some_and(|info| info.instruction == *instruct)
+        })
+    }
+}
+
+// addressing quirks of the real chips, code can depend on them so they are on by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // JMP ($xxFF) reads the high byte from $xx00, fixed on the 65C02
+    pub indirect_jump_wrap: bool,
+    // zp,X zp,Y and (zp,X) wrap around inside the zero page
+    pub zero_page_wrap: bool,
+    // a zero page pointer at $FF reads its high byte from $00
+    pub pointer_wrap: bool,
+}
+
+impl fmt::Display for Variant {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.to_str())
     }
@@ -1,4 +1,11 @@
 pub mod asm;
-pub mod memory;
+pub mod fast;
+pub mod history;
+pub mod input;
 pub mod instruct;
+pub mod loader;
 pub mod m6502;
+pub mod memory;
+pub mod runner;
+pub mod scheduler;
+pub mod snapshot;
@@ -0,0 +1,133 @@
+// Program images in the formats other tools produce.
+//
+// Raw binaries have no address of their own and go where they are told. PRG files start
+// with their load address, the Commodore way. Intel HEX carries an address on every record,
+// only the 64 KiB the processor can see are accepted.
+
+use std::io;
+use std::path::Path;
+
+use crate::memory::Memory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Raw,
+    Prg,
+    IntelHex,
+}
+
+impl Format {
+    // guesses the format from the file extension, anything unknown is raw
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Format {
+        let extension = path.as_ref().extension().and_then(|e| e.to_str());
+        match extension.map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("prg") => Format::Prg,
+            Some("hex" | "ihex") => Format::IntelHex,
+            _ => Format::Raw,
+        }
+    }
+}
+
+// puts the image into memory, `addr` replaces the load address of a PRG file and is where a
+// raw image starts (0 if not given), returns the lowest and highest address written
+pub fn load(
+    data: &[u8],
+    format: Format,
+    addr: Option<u16>,
+    memory: &mut dyn Memory,
+) -> io::Result<Option<(u16, u16)>> {
+    let mut bytes = Vec::new();
+    match format {
+        Format::Raw => {
+            let start = addr.unwrap_or(0);
+            bytes.extend(
+                data.iter()
+                    .enumerate()
+                    .map(|(i, b)| (start as usize + i, *b)),
+            );
+        }
+        Format::Prg => {
+            let [low, high, image @ ..] = data else {
+                return Err(invalid("prg file without a load address"));
+            };
+            let start = addr.unwrap_or(u16::from_le_bytes([*low, *high]));
+            bytes.extend(
+                image
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| (start as usize + i, *b)),
+            );
+        }
+        Format::IntelHex => {
+            let text = std::str::from_utf8(data).map_err(|_| invalid("intel hex is not text"))?;
+            bytes = intel_hex(text)?;
+            // moves the whole image so its lowest byte lands on `addr`
+            if let (Some(addr), Some(lowest)) = (addr, bytes.iter().map(|(a, _)| *a).min()) {
+                for (a, _) in bytes.iter_mut() {
+                    *a = *a - lowest + addr as usize;
+                }
+            }
+        }
+    }
+    if bytes.iter().any(|(a, _)| *a > 0xFFFF) {
+        return Err(invalid("image does not fit below $10000"));
+    }
+    for (a, b) in &bytes {
+        memory.set(*a as u16, *b);
+    }
+    let lowest = bytes.iter().map(|(a, _)| *a as u16).min();
+    let highest = bytes.iter().map(|(a, _)| *a as u16).max();
+    Ok(lowest.zip(highest))
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+// the data records of an Intel HEX file, as (address, byte)
+fn intel_hex(text: &str) -> io::Result<Vec<(usize, u8)>> {
+    let mut bytes = Vec::new();
+    // set by the extended address records
+    let mut base = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |reason: &str| invalid(&format!("line {}: {reason}", i + 1));
+        let record = line
+            .strip_prefix(':')
+            .filter(|hex| hex.len() % 2 == 0)
+            .and_then(|hex| {
+                (0..hex.len())
+                    .step_by(2)
+                    .map(|j| u8::from_str_radix(hex.get(j..j + 2)?, 16).ok())
+                    .collect::<Option<Vec<u8>>>()
+            })
+            .ok_or_else(|| error("not a hex record"))?;
+        let [len, high, low, kind, rest @ ..] = record.as_slice() else {
+            return Err(error("record is too short"));
+        };
+        if rest.len() != *len as usize + 1 {
+            return Err(error("record length does not match"));
+        }
+        if record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(error("bad checksum"));
+        }
+        let payload = &rest[..*len as usize];
+        let offset = u16::from_be_bytes([*high, *low]) as usize;
+        match (kind, payload) {
+            (0x00, _) => {
+                let start = base + offset;
+                bytes.extend(payload.iter().enumerate().map(|(j, b)| (start + j, *b)));
+            }
+            (0x01, _) => break,
+            (0x02, [high, low]) => base = (u16::from_be_bytes([*high, *low]) as usize) << 4,
+            (0x04, [high, low]) => base = (u16::from_be_bytes([*high, *low]) as usize) << 16,
+            // start addresses, the processor starts from its reset vector instead
+            (0x03 | 0x05, _) => {}
+            _ => return Err(error("unknown record type")),
+        }
+    }
+    Ok(bytes)
+}
@@ -1,16 +1,21 @@
-use crate::instruct::{AddressType, Instruct, InstructionInfo};
+use crate::instruct::{AddressType, Instruct, InstructionInfo, Quirks, Variant};
+use crate::memory::Bus;
 use std::fmt;
 
 const NEGATIVE: u8 = 0b10000000;
 const OVERFLOW: u8 = 0b01000000;
-const _IGNORED: u8 = 0b00100000;
+const IGNORED: u8 = 0b00100000;
 const BREAK: u8 = 0b00010000;
-const DECIMAL: u8 = 0b00001000;
-const INTERRUPT: u8 = 0b00000100;
+pub(crate) const DECIMAL: u8 = 0b00001000;
+pub(crate) const INTERRUPT: u8 = 0b00000100;
 const ZERO: u8 = 0b00000010;
 const CARRY: u8 = 0b00000001;
 
-#[derive(fmt::Debug, Clone)]
+pub(crate) const NMI_VECTOR: u16 = 0xFFFA;
+pub(crate) const RESET_VECTOR: u16 = 0xFFFC;
+pub(crate) const IRQ_VECTOR: u16 = 0xFFFE;
+
+#[derive(fmt::Debug, Clone, PartialEq)]
 pub struct Registers {
     // Program Counter
     pub pc: u16,
@@ -26,6 +31,12 @@ pub struct Registers {
     pub sp: u8,
 }
 
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Registers {
     pub fn new() -> Self {
         Registers {
@@ -35,24 +46,52 @@ impl Registers {
             yr: 0x00,
             //    nv-bdizc
             sr: 0b00000110,
-            sp: 0xFD,
+            // the reset sequence decrements it three times, leaving it at 0xFD
+            sp: 0x00,
         }
     }
-    pub fn status_add(&mut self, flags: u8) -> () {
+    pub fn status_add(&mut self, flags: u8) {
         self.sr |= flags;
     }
-    pub fn status_remove(&mut self, flags: u8) -> () {
+    pub fn status_remove(&mut self, flags: u8) {
         self.sr &= !flags;
     }
     pub fn status_has(&self, flags: u8) -> bool {
         (self.sr & flags) == flags
     }
+    pub fn status_set(&mut self, flags: u8, value: bool) {
+        if value {
+            self.status_add(flags);
+        } else {
+            self.status_remove(flags);
+        }
+    }
+    // sets the negative and zero flags from a result
+    pub fn set_nz(&mut self, value: u8) {
+        self.status_set(NEGATIVE, value & NEGATIVE != 0);
+        self.status_set(ZERO, value == 0);
+    }
+    // B and the ignored bit only exist in the copy pushed to the stack
+    pub fn status_push(&self, brk: bool) -> u8 {
+        if brk {
+            self.sr | BREAK | IGNORED
+        } else {
+            (self.sr | IGNORED) & !BREAK
+        }
+    }
+    pub fn status_pull(&mut self, value: u8) {
+        self.sr = value & !(BREAK | IGNORED);
+    }
+    pub fn compare(&mut self, register: u8, value: u8) {
+        self.status_set(CARRY, register >= value);
+        self.set_nz(register.wrapping_sub(value));
+    }
     pub fn fmt_status(&self) -> String {
         format!(
             "{}{}-{}{}{}{}{}",
             if self.status_has(NEGATIVE) { 'N' } else { 'n' },
             if self.status_has(OVERFLOW) { 'V' } else { 'v' },
-            if self.status_has(BREAK) { 'b' } else { 'B' },
+            if self.status_has(BREAK) { 'B' } else { 'b' },
             if self.status_has(DECIMAL) { 'D' } else { 'd' },
             if self.status_has(INTERRUPT) { 'I' } else { 'i' },
             if self.status_has(ZERO) { 'Z' } else { 'z' },
@@ -76,11 +115,17 @@ pub struct TimingState {
     sd2: bool,
 }
 
+impl Default for TimingState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TimingState {
     pub fn new() -> TimingState {
         TimingState {
-            t0: true,
-            t1: false,
+            t0: false,
+            t1: true,
             tp: false,
             t2: false,
             t3: false,
@@ -108,6 +153,53 @@ impl TimingState {
             sd2: false,
         }
     }
+
+    // packs the flags in the order they are declared, used by snapshots
+    pub(crate) fn to_bits(&self) -> u16 {
+        [
+            self.t0, self.t1, self.tp, self.t2, self.t3, self.t4, self.t5, self.t6, self.v0,
+            self.sd1, self.sd2,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, set)| bits | ((*set as u16) << i))
+    }
+
+    pub(crate) fn from_bits(bits: u16) -> TimingState {
+        let bit = |i: u16| bits & (1 << i) != 0;
+        TimingState {
+            t0: bit(0),
+            t1: bit(1),
+            tp: bit(2),
+            t2: bit(3),
+            t3: bit(4),
+            t4: bit(5),
+            t5: bit(6),
+            t6: bit(7),
+            v0: bit(8),
+            sd1: bit(9),
+            sd2: bit(10),
+        }
+    }
+
+    // T1 is the opcode fetch cycle, the boundary between two instructions
+    pub fn is_fetch(&self) -> bool {
+        self.t1
+    }
+
+    pub fn is_clear(&self) -> bool {
+        !(self.t0
+            || self.t1
+            || self.tp
+            || self.t2
+            || self.t3
+            || self.t4
+            || self.t5
+            || self.t6
+            || self.v0
+            || self.sd1
+            || self.sd2)
+    }
 }
 
 impl fmt::Debug for TimingState {
@@ -124,6 +216,13 @@ impl fmt::Debug for TimingState {
             write!(f, "T1")?;
             first = false;
         }
+        if self.tp {
+            if !first {
+                write!(f, " + ")?;
+            }
+            write!(f, "TP")?;
+            first = false;
+        }
         if self.t2 {
             if !first {
                 write!(f, " + ")?;
@@ -186,7 +285,11 @@ impl fmt::Debug for TimingState {
 #[derive(Debug, Clone)]
 pub struct State {
     pub registers: Registers,
-    pub total_cycles: i32,
+    // cycles the processor ran, it stops while RES is held but is never cleared
+    pub total_cycles: u64,
+    // half cycles of the clock, unlike `total_cycles` it keeps counting while the processor
+    // is held in reset, jammed or waiting
+    pub half_cycles: u64,
 
     pub ab: u16,
     pub db: u8,
@@ -197,19 +300,156 @@ pub struct State {
     // fetched instruction
     pub pd: u8,
 
+    // internal address latch (effective address)
+    pub ad: u16,
+    // pointer used by indexed and indirect modes
+    pub ptr: u16,
+    // value being modified by read-modify-write instructions
+    pub alu: u8,
+    // length of the current instruction, including page crossing and branch penalties
+    pub cycles: u8,
+
     pub clock1: bool,
     pub clock2: bool,
 
     pub timing: TimingState,
     pub next_timing: TimingState,
 
-    // sync output
+    // sync output, high while the opcode is fetched
     pub sync: bool,
 
     // interrupt inputs
     pub res: bool,
     pub irq: bool,
     pub nmi: bool,
+    // ready input, holding it low stalls the processor on the next read cycle
+    pub rdy: bool,
+    // set overflow input, asserting it sets V
+    pub so: bool,
+
+    // so level seen on the previous cycle, used to detect the edge
+    pub so_prev: bool,
+    // RDY was low during a read, the cycle is repeated until it goes high
+    pub stalled: bool,
+
+    // nmi level seen on the previous cycle, used to detect the edge
+    pub nmi_prev: bool,
+    // an nmi edge was detected and has not been serviced yet
+    pub nmi_pending: bool,
+    // the next instruction is replaced by the interrupt sequence
+    pub interrupt: bool,
+    // the reset sequence is running
+    pub resetting: bool,
+
+    // set when the processor jammed, only a reset gets it running again
+    pub fault: Option<Fault>,
+    // runs the undocumented opcodes instead of jamming on them, NMOS parts only
+    pub undocumented: bool,
+
+    pub variant: Variant,
+    // replaces the addressing quirks of the variant
+    pub quirks: Option<Quirks>,
+    // WAI stopped the processor until an interrupt line goes active
+    pub waiting: bool,
+    // the 6510 I/O port
+    pub port: IoPort,
+}
+
+// the level of every pin during the half cycle that ran last, `true` is high except for the
+// active low inputs, which are `true` while asserted like on `State`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pins {
+    pub phi1: bool,
+    pub phi2: bool,
+    pub ab: u16,
+    pub db: u8,
+    pub rw: bool,
+    pub sync: bool,
+    pub rdy: bool,
+    pub res: bool,
+    pub irq: bool,
+    pub nmi: bool,
+    pub so: bool,
+}
+
+impl fmt::Display for Pins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flag = |set: bool, name: &'static str| if set { name } else { "" };
+        write!(
+            f,
+            "{} {:04x} {:02x} {} {:4} {:3} {:3} {:3} {:3} {:2}",
+            if self.phi1 { "φ1" } else { "φ2" },
+            self.ab,
+            self.db,
+            if self.rw { "R" } else { "W" },
+            flag(self.sync, "SYNC"),
+            flag(!self.rdy, "RDY"),
+            flag(self.res, "RES"),
+            flag(self.irq, "IRQ"),
+            flag(self.nmi, "NMI"),
+            flag(self.so, "SO"),
+        )
+    }
+}
+
+// the 6510 on-chip I/O port, $00 sets the direction of each pin (1 = output), $01 is the data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IoPort {
+    pub direction: u8,
+    pub output: u8,
+    // level of the pins from outside, inputs are pulled up
+    pub input: u8,
+}
+
+impl IoPort {
+    pub fn new() -> IoPort {
+        IoPort {
+            direction: 0,
+            output: 0,
+            input: 0xFF,
+        }
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        if addr == 0 {
+            self.direction
+        } else {
+            (self.output & self.direction) | (self.input & !self.direction)
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, value: u8) {
+        if addr == 0 {
+            self.direction = value;
+        } else {
+            self.output = value;
+        }
+    }
+}
+
+impl Default for IoPort {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// an opcode the processor can't execute, the real chip locks up on these
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fault {
+    pub pc: u16,
+    pub op_code: u8,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "jammed on {:02x} at {:04x}", self.op_code, self.pc)
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl State {
@@ -217,15 +457,21 @@ impl State {
         State {
             registers: Registers::new(),
             total_cycles: 0,
+            half_cycles: 0,
 
             ir: 0,
             pd: 0,
 
+            ad: 0,
+            ptr: 0,
+            alu: 0,
+            cycles: 7,
+
             ab: 0,
             db: 0,
             rw: false,
 
-            clock1: false,
+            clock1: true,
             clock2: false,
 
             timing: TimingState::new(),
@@ -236,254 +482,1263 @@ impl State {
             res: false,
             irq: false,
             nmi: false,
+            rdy: true,
+            so: false,
+
+            so_prev: false,
+            stalled: false,
+
+            nmi_prev: false,
+            nmi_pending: false,
+            interrupt: false,
+            resetting: true,
+
+            fault: None,
+            undocumented: false,
+
+            variant: Variant::Nmos6502,
+            quirks: None,
+            waiting: false,
+            port: IoPort::new(),
         }
     }
+
+    pub fn pins(&self) -> Pins {
+        Pins {
+            // the clock flags already point at the next half cycle
+            phi1: self.clock2,
+            phi2: self.clock1,
+            ab: self.ab,
+            db: self.db,
+            rw: self.rw,
+            sync: self.sync,
+            rdy: self.rdy,
+            res: self.res,
+            irq: self.irq,
+            nmi: self.nmi,
+            so: self.so,
+        }
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks.unwrap_or(self.variant.quirks())
+    }
 }
 
 pub fn step(state: &mut State) {
+    state.half_cycles += 1;
     if state.res {
-        state.next_timing = TimingState::clear();
-        state.next_timing.t2 = true;
-        state.ir = 0;
-        state.registers.sr &= !BREAK;
+        // the processor is held until RES is released, then runs the reset sequence
+        state.next_timing = TimingState::new();
+        state.resetting = true;
+        state.interrupt = false;
+        state.fault = None;
+        state.waiting = false;
+        state.stalled = false;
+        state.port = IoPort::new();
+        state.rw = true;
+        state.sync = false;
         state.clock1 = true;
         state.clock2 = false;
-        state.total_cycles = 0;
         return;
     };
-    if state.irq || state.nmi {
-        state.registers.sr &= INTERRUPT;
-    };
+    if state.clock1 {
+        sample_so(state);
+    }
+    if state.fault.is_some() || (state.clock1 && waiting(state)) {
+        return;
+    }
     if state.clock1 {
         state.total_cycles += 1;
         state.clock1 = false;
         state.clock2 = true;
-        step1(state);
+        // a stalled cycle keeps the same address on the bus
+        if !state.stalled {
+            step1(state);
+        }
     } else if state.clock2 {
         state.clock1 = true;
         state.clock2 = false;
-        step2(state);
+        // the NMOS parts ignore RDY on write cycles
+        state.stalled = !state.rdy && (state.rw || state.variant.is_cmos());
+        if !state.stalled {
+            step2(state);
+        }
     };
 }
 
+// runs one half cycle, doing the bus transaction before φ2
+pub fn half_step<B: Bus + ?Sized>(state: &mut State, bus: &mut B) {
+    if state.clock2 {
+        if state.rw {
+            state.db = bus_read(state, bus, state.ab);
+        } else {
+            bus_write(state, bus, state.ab, state.db);
+        }
+    }
+    step(state);
+}
+
+// runs the given number of half cycles, returns the pins of each
+pub fn trace<B: Bus + ?Sized>(state: &mut State, bus: &mut B, half_cycles: usize) -> Vec<Pins> {
+    (0..half_cycles)
+        .map(|_| {
+            half_step(state, bus);
+            state.pins()
+        })
+        .collect()
+}
+
+// runs φ1 and φ2 of one cycle
+pub fn cycle<B: Bus + ?Sized>(state: &mut State, bus: &mut B) {
+    half_step(state, bus);
+    if state.clock2 {
+        half_step(state, bus);
+    }
+}
+
+pub fn run_cycles<B: Bus + ?Sized>(state: &mut State, bus: &mut B, cycles: u32) {
+    for _ in 0..cycles {
+        cycle(state, bus);
+    }
+}
+
+// runs until the current instruction (or interrupt sequence) is done, returns the cycles taken
+pub fn run_instruction<B: Bus + ?Sized>(state: &mut State, bus: &mut B) -> u32 {
+    if !state.res && (state.fault.is_some() || waiting(state)) {
+        return 0;
+    }
+    let mut cycles = 0;
+    loop {
+        cycle(state, bus);
+        cycles += 1;
+        if instruction_done(state) {
+            return cycles;
+        }
+    }
+}
+
+// true after the last cycle of an instruction, or when the processor can't get there
+pub(crate) fn instruction_done(state: &State) -> bool {
+    state.next_timing.t1 || state.res || state.fault.is_some() || state.waiting || state.stalled
+}
+
+// looks up an opcode, the undocumented ones only when they are enabled
+pub(crate) fn lookup(state: &State, op_code: u8) -> Option<&'static InstructionInfo> {
+    state.variant.decode(op_code).filter(|_| {
+        state.undocumented || state.variant.is_cmos() || !Instruct::is_undocumented(op_code)
+    })
+}
+
+// the 6510 answers reads of $00 and $01 from its I/O port, writes go to the bus as well
+pub(crate) fn bus_read<B: Bus + ?Sized>(state: &State, bus: &mut B, addr: u16) -> u8 {
+    let value = bus.read(addr);
+    if state.variant == Variant::Mos6510 && addr < 2 {
+        state.port.read(addr)
+    } else {
+        value
+    }
+}
+
+pub(crate) fn bus_write<B: Bus + ?Sized>(state: &mut State, bus: &mut B, addr: u16, value: u8) {
+    bus.write(addr, value);
+    if state.variant == Variant::Mos6510 && addr < 2 {
+        state.port.write(addr, value);
+    }
+}
+
+// a falling edge on SO sets the overflow flag
+pub(crate) fn sample_so(state: &mut State) {
+    if state.so && !state.so_prev {
+        state.registers.status_add(OVERFLOW);
+    }
+    state.so_prev = state.so;
+}
+
+// checks if a WAI is still waiting, an active interrupt line wakes the processor up
+pub(crate) fn waiting(state: &mut State) -> bool {
+    if !state.waiting {
+        return false;
+    }
+    if state.nmi && !state.nmi_prev {
+        state.nmi_pending = true;
+    }
+    state.nmi_prev = state.nmi;
+    if state.irq || state.nmi_pending {
+        state.waiting = false;
+        // with interrupts disabled an IRQ just continues with the next instruction
+        poll_interrupts(state);
+    }
+    state.waiting
+}
+
+// looks up an opcode, jams the processor if it doesn't exist
+fn decode(state: &mut State, op_code: u8) -> Option<&'static InstructionInfo> {
+    let info = lookup(state, op_code);
+    if info.is_none() {
+        state.fault = Some(Fault {
+            pc: state.registers.pc.wrapping_sub(1),
+            op_code,
+        });
+    }
+    info
+}
+
 fn step1(state: &mut State) {
     state.timing = std::mem::replace(&mut state.next_timing, TimingState::clear());
 
     state.rw = true;
+    state.sync = state.timing.t1;
     if state.timing.t2 {
-        if state.irq || state.nmi {
-            state.next_timing = TimingState::clear();
-            state.next_timing.t2 = true;
-            state.registers.sr &= !BREAK;
-            state.ir = 0;
-            return;
+        state.ir = state.pd;
+    };
+    if state.timing.t1 {
+        if state.interrupt || state.resetting {
+            state.ab = state.registers.pc;
         } else {
-            state.ir = state.pd;
+            fetch(state);
         }
-    };
+        return;
+    }
     let op_code = state.ir;
-    let InstructionInfo {
-        instruction,
-        mode,
-        ..
-    } = Instruct::from_op_code(op_code).unwrap();
-    if state.timing.t2 {
-        // state.registers.pc += 1;
+    let Some(InstructionInfo {
+        instruction, mode, ..
+    }) = decode(state, op_code)
+    else {
+        return;
+    };
+    if state.timing.sd2 && decimal_cycle(state, instruction) {
+        state.ab = state.registers.pc;
+        return;
     }
     match mode {
         AddressType::Impl => match instruction {
             Instruct::BRK => {
-                if state.timing.t6 {
-                    state.ab = 0xFFFC;
+                if state.timing.t2 {
+                    if state.interrupt || state.resetting {
+                        state.ab = state.registers.pc;
+                    } else {
+                        fetch(state);
+                    }
+                } else if state.timing.t3 || state.timing.t4 || state.timing.t5 {
+                    let value = if state.timing.t3 {
+                        (state.registers.pc >> 8) as u8
+                    } else if state.timing.t4 {
+                        state.registers.pc as u8
+                    } else {
+                        state.registers.status_push(!state.interrupt)
+                    };
+                    if state.resetting {
+                        // reset goes through the pushes with the bus held in read mode
+                        state.ab = stack_addr(state);
+                    } else {
+                        push(state, value);
+                    }
+                } else if state.timing.t6 {
+                    // an nmi arriving before this point hijacks the BRK or IRQ sequence
+                    state.ptr = if state.resetting {
+                        RESET_VECTOR
+                    } else if state.nmi_pending {
+                        state.nmi_pending = false;
+                        NMI_VECTOR
+                    } else {
+                        IRQ_VECTOR
+                    };
+                    state.ab = state.ptr;
+                } else if state.timing.t0 {
+                    state.ab = state.ptr + 1;
                 };
-                if state.timing.t0 {
-                    state.ab = 0xFFFD;
+            }
+            Instruct::RTI | Instruct::PLA | Instruct::PLP | Instruct::PLX | Instruct::PLY => {
+                if state.timing.t2 {
+                    state.ab = state.registers.pc;
+                } else {
+                    state.ab = stack_addr(state);
                 };
             }
-            _ => unimplemented!(),
+            Instruct::RTS => {
+                if state.timing.t2 || state.timing.t0 {
+                    state.ab = state.registers.pc;
+                } else {
+                    state.ab = stack_addr(state);
+                };
+            }
+            Instruct::PHA | Instruct::PHP | Instruct::PHX | Instruct::PHY => {
+                if state.timing.t2 {
+                    state.ab = state.registers.pc;
+                } else if state.timing.t0 {
+                    let value = match instruction {
+                        Instruct::PHA => state.registers.ac,
+                        Instruct::PHX => state.registers.xr,
+                        Instruct::PHY => state.registers.yr,
+                        _ => state.registers.status_push(true),
+                    };
+                    push(state, value);
+                };
+            }
+            _ => {
+                state.ab = state.registers.pc;
+            }
         },
+        AddressType::Accumulator => {
+            state.ab = state.registers.pc;
+        }
         AddressType::Immediate => {
-            match instruction {
-                _ => {
-                    if state.timing.t2 {
-                        state.registers.pc += 1;
-                        state.ab = state.registers.pc;
-                    };
-                    if state.timing.t1 {
-                        state.registers.pc += 1;
-                    };
-                },
+            fetch(state);
+        }
+        AddressType::Relative => {
+            if state.timing.t2 {
+                fetch(state);
+            } else {
+                // dummy reads while the new program counter is computed
+                state.ab = state.registers.pc;
             };
         }
         AddressType::ZeroPage => {
-            match instruction {
-                Instruct::STA => {
-                    if state.timing.t2 {
-                        state.registers.pc += 1;
-                        state.ab = state.registers.pc;
-                    };
-                    if state.timing.t0 {
-                        state.registers.pc += 1;
-                        state.ab = state.pd as u16;
-                        state.rw = false;
-                        state.db = state.registers.ac;
-                    }
-                }
-                _ => {
-                    if state.timing.t2 {
-                        state.registers.pc += 1;
-                        state.ab = state.registers.pc;
-                    };
-                    if state.timing.t0 {
-                        state.registers.pc += 1;
-                        state.ab = state.pd as u16;
-                    };
-                },
+            if state.timing.t2 {
+                fetch(state);
+            } else {
+                operand_step1(state, instruction);
             };
         }
-        AddressType::ZeroPageX => {
-            match instruction => {
-                Instruct::ADC => {
-                    if state.timing.t2 {
-                        state.registers.pc += 1;
-                        state.ab = state.registers.pc;
-                    };
-                    if state.timing.t3 {
-                        state.registers.pc += 1;
-                        state.ab = state.pd as u16;
-                    }
-                    if state.timing.t0 {
-                        state.ab = (state.pd + state.registers.x) as u16;
-                    }
-                }
-                _ => unimplemented!()
+        AddressType::ZeroPageX | AddressType::ZeroPageY => {
+            if state.timing.t2 {
+                fetch(state);
+            } else if state.timing.t3 {
+                state.ab = state.ad;
+            } else {
+                operand_step1(state, instruction);
+            };
+        }
+        AddressType::Absolute => match instruction {
+            Instruct::JSR => {
+                if state.timing.t2 {
+                    fetch(state);
+                } else if state.timing.t3 {
+                    state.ab = stack_addr(state);
+                } else if state.timing.t4 {
+                    let pch = (state.registers.pc >> 8) as u8;
+                    push(state, pch);
+                } else if state.timing.t5 {
+                    let pcl = state.registers.pc as u8;
+                    push(state, pcl);
+                } else if state.timing.t0 {
+                    state.ab = state.registers.pc;
+                };
+            }
+            Instruct::JMP => {
+                fetch(state);
+            }
+            Instruct::NOP if op_code == 0x5C => {
+                if state.timing.t2 || state.timing.t3 {
+                    fetch(state);
+                } else if state.timing.t4 {
+                    // the high byte is ignored, it reads from the last page
+                    state.ab = 0xFF00 | (state.ad & 0x00FF);
+                } else {
+                    state.ab = 0xFFFF;
+                };
             }
+            _ => {
+                if state.timing.t2 || state.timing.t3 {
+                    fetch(state);
+                } else {
+                    operand_step1(state, instruction);
+                };
+            }
+        },
+        AddressType::AbsoluteX | AddressType::AbsoluteY => {
+            if state.timing.t2 || state.timing.t3 {
+                fetch(state);
+            } else if state.timing.t4 {
+                // dummy read before the high byte of the address is fixed
+                state.ab = fixup_addr(state);
+            } else {
+                operand_step1(state, instruction);
+            };
+        }
+        AddressType::Indirect => {
+            let cmos = state.variant.is_cmos();
+            if state.timing.t2 || state.timing.t3 {
+                fetch(state);
+            } else if state.timing.t4 && cmos {
+                // the 65C02 takes an extra cycle to fix the pointer bug
+                state.ab = state.registers.pc.wrapping_sub(1);
+            } else if state.timing.t4 || state.timing.t5 {
+                state.ab = state.ptr;
+            } else if state.timing.t0 {
+                state.ab = indirect_high(state, state.ptr);
+            };
+        }
+        AddressType::IndirectX => {
+            if state.timing.t2 {
+                fetch(state);
+            } else if state.timing.t3 || state.timing.t4 {
+                state.ab = state.ptr;
+            } else if state.timing.t5 {
+                state.ab = pointer_high(state, state.ptr);
+            } else {
+                operand_step1(state, instruction);
+            };
+        }
+        AddressType::IndirectY => {
+            if state.timing.t2 {
+                fetch(state);
+            } else if state.timing.t3 {
+                state.ab = state.ptr;
+            } else if state.timing.t4 {
+                state.ab = pointer_high(state, state.ptr);
+            } else if state.timing.t5 {
+                // dummy read before the high byte of the address is fixed
+                state.ab = fixup_addr(state);
+            } else {
+                operand_step1(state, instruction);
+            };
+        }
+        AddressType::ZeroPageIndirect => {
+            if state.timing.t2 {
+                fetch(state);
+            } else if state.timing.t3 {
+                state.ab = state.ptr;
+            } else if state.timing.t4 {
+                state.ab = pointer_high(state, state.ptr);
+            } else {
+                operand_step1(state, instruction);
+            };
+        }
+        AddressType::AbsoluteIndirectX => {
+            if state.timing.t2 || state.timing.t3 {
+                fetch(state);
+            } else if state.timing.t4 {
+                // dummy read while X is added to the pointer
+                state.ab = state.registers.pc.wrapping_sub(1);
+            } else if state.timing.t5 {
+                state.ab = state.ptr;
+            } else if state.timing.t0 {
+                state.ab = state.ptr.wrapping_add(1);
+            };
         }
-        _ => unimplemented!(),
     };
-    if state.timing.t1 {
-        state.ab = state.registers.pc;
-    }
 }
 
 fn step2(state: &mut State) {
     state.pd = state.db;
 
+    if state.nmi && !state.nmi_prev {
+        state.nmi_pending = true;
+    }
+    state.nmi_prev = state.nmi;
+
+    if state.timing.t1 {
+        state.next_timing = TimingState::clear();
+        state.next_timing.t2 = true;
+        if state.interrupt || state.resetting {
+            // the fetched opcode is discarded and a BRK is executed instead
+            state.pd = 0;
+            state.cycles = 7;
+            return;
+        }
+        let Some(next_instruct) = decode(state, state.pd) else {
+            return;
+        };
+        state.cycles = next_instruct.cycles;
+        if next_instruct.cycles == 1 {
+            // the single cycle NOPs of the 65C02 are done with the opcode fetch
+            state.ir = state.pd;
+            state.next_timing = TimingState::new();
+            poll_interrupts(state);
+        } else if next_instruct.cycles == 2 {
+            state.next_timing.t0 = true;
+            poll_interrupts(state);
+        }
+        return;
+    }
+
     let op_code = state.ir;
+    let Some(info) = decode(state, op_code) else {
+        return;
+    };
     let InstructionInfo {
-        instruction,
-        mode,
-        ..
-    } = Instruct::from_op_code(op_code).unwrap();
+        instruction, mode, ..
+    } = info;
+    if state.timing.sd2 && decimal_cycle(state, instruction) {
+        state.next_timing.t1 = true;
+        return;
+    }
     match mode {
         AddressType::Impl => match instruction {
             Instruct::BRK => {
-                if state.timing.t3 {
-                    state.ir = 0;
-                    state.registers.sp = 0;
-                    state.registers.pc = 0;
+                if state.timing.t3 || state.timing.t4 {
+                    state.registers.sp = state.registers.sp.wrapping_sub(1);
+                } else if state.timing.t5 {
+                    state.registers.sp = state.registers.sp.wrapping_sub(1);
+                    state.registers.status_add(INTERRUPT);
+                    if state.variant.is_cmos() {
+                        state.registers.status_remove(DECIMAL);
+                    }
                 } else if state.timing.t6 {
-                    state.registers.pc = state.db as u16;
-                    state.next_timing.t0 = true;
+                    state.ad = state.pd as u16;
+                } else if state.timing.t0 {
+                    state.registers.pc = state.ad | ((state.pd as u16) << 8);
+                    state.interrupt = false;
+                    state.resetting = false;
+                }
+            }
+            Instruct::RTI => {
+                if state.timing.t3 {
+                    state.registers.sp = state.registers.sp.wrapping_add(1);
+                } else if state.timing.t4 {
+                    state.registers.status_pull(state.pd);
+                    state.registers.sp = state.registers.sp.wrapping_add(1);
+                } else if state.timing.t5 {
+                    state.ad = state.pd as u16;
+                    state.registers.sp = state.registers.sp.wrapping_add(1);
                 } else if state.timing.t0 {
-                    state.registers.pc = (state.db as u16) << 8;
+                    state.registers.pc = state.ad | ((state.pd as u16) << 8);
+                }
+            }
+            Instruct::RTS => {
+                if state.timing.t3 {
+                    state.registers.sp = state.registers.sp.wrapping_add(1);
+                } else if state.timing.t4 {
+                    state.ad = state.pd as u16;
+                    state.registers.sp = state.registers.sp.wrapping_add(1);
+                } else if state.timing.t5 {
+                    state.registers.pc = state.ad | ((state.pd as u16) << 8);
+                } else if state.timing.t0 {
+                    state.registers.pc = state.registers.pc.wrapping_add(1);
+                }
+            }
+            Instruct::PHA | Instruct::PHP | Instruct::PHX | Instruct::PHY => {
+                if state.timing.t0 {
+                    state.registers.sp = state.registers.sp.wrapping_sub(1);
+                }
+            }
+            Instruct::PLA | Instruct::PLP | Instruct::PLX | Instruct::PLY => {
+                if state.timing.t3 {
+                    state.registers.sp = state.registers.sp.wrapping_add(1);
+                } else if state.timing.t0 {
+                    match instruction {
+                        Instruct::PLA => {
+                            state.registers.ac = state.pd;
+                            state.registers.set_nz(state.pd);
+                        }
+                        Instruct::PLX => {
+                            state.registers.xr = state.pd;
+                            state.registers.set_nz(state.pd);
+                        }
+                        Instruct::PLY => {
+                            state.registers.yr = state.pd;
+                            state.registers.set_nz(state.pd);
+                        }
+                        _ => {
+                            state.registers.status_pull(state.pd);
+                        }
+                    };
+                }
+            }
+            _ => {
+                if state.timing.t0 {
+                    execute(state, instruction);
                 }
             }
-            _ => unimplemented!(),
         },
+        AddressType::Accumulator => {
+            if state.timing.t0 {
+                let value = state.registers.ac;
+                state.registers.ac =
+                    modify(&mut state.registers, state.variant, instruction, value);
+            }
+        }
         AddressType::Immediate => {
-            match instruction {
-                Instruct::ADC => {
-                    if state.timing.t2 {
-                        ins_adc(state);
-                    };
+            if state.timing.t0 {
+                execute(state, instruction);
+            }
+        }
+        AddressType::Relative => {
+            if state.timing.t2 {
+                if branch_taken(&state.registers, instruction) {
+                    state.ad = state.pd as u16;
+                    state.cycles += 1;
+                    state.next_timing.t3 = true;
                 }
-                Instruct::LDA => {
-                    if state.timing.t2 {
-                        state.registers.ac = state.pd;
-                    };
+            } else if state.timing.t3 {
+                let pc = state.registers.pc;
+                let target = pc.wrapping_add(state.ad as u8 as i8 as u16);
+                if (target & 0xFF00) != (pc & 0xFF00) {
+                    // the low byte is added first, fixing the high byte takes another cycle
+                    state.registers.pc = (pc & 0xFF00) | (target & 0x00FF);
+                    state.ad = target;
+                    state.cycles += 1;
+                    state.next_timing.t4 = true;
+                    poll_interrupts(state);
+                } else {
+                    // a taken branch that stays on the same page doesn't poll interrupts again
+                    state.registers.pc = target;
+                    state.next_timing.t1 = true;
                 }
-                _ => unimplemented!(),
-            };
+            } else if state.timing.t4 {
+                state.registers.pc = state.ad;
+                state.next_timing.t1 = true;
+            }
         }
         AddressType::ZeroPage => {
-            match instruction {
-                Instruct::ADC => {
-                    if state.timing.t2 {
-                        ins_adc(state);
-                        state.next_timing.t0 = true;
-                    };
-                },
-                Instruct::LDA => {
-                    if state.timing.t2 {
-                        state.registers.ac = state.pd;
-                        state.next_timing.t0 = true;
-                    };
+            if state.timing.t2 {
+                state.ad = state.pd as u16;
+            } else {
+                operand_step2(state, instruction);
+            }
+        }
+        AddressType::ZeroPageX | AddressType::ZeroPageY => {
+            if state.timing.t2 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t3 {
+                let index = match mode {
+                    AddressType::ZeroPageX => state.registers.xr,
+                    _ => state.registers.yr,
+                };
+                state.ad = zero_page_index(state, state.ad as u8, index);
+            } else {
+                operand_step2(state, instruction);
+            }
+        }
+        AddressType::Absolute => match instruction {
+            Instruct::JSR => {
+                if state.timing.t2 {
+                    state.ad = state.pd as u16;
+                } else if state.timing.t4 || state.timing.t5 {
+                    state.registers.sp = state.registers.sp.wrapping_sub(1);
+                } else if state.timing.t0 {
+                    state.registers.pc = state.ad | ((state.pd as u16) << 8);
                 }
-                Instruct::STA => {
-                    if state.timing.t2 {
-                        state.next_timing.t0 = true;
-                    }
+            }
+            Instruct::JMP => {
+                if state.timing.t2 {
+                    state.ad = state.pd as u16;
+                } else if state.timing.t0 {
+                    state.registers.pc = state.ad | ((state.pd as u16) << 8);
                 }
-                _ => unimplemented!(),
-            };
-        },
-        AddressType::ZeroPageX => {
-            match instruction {
-                Instruct::ADC => {
-                    if state.timing.t3 {
-                        state.next_timing.t0 = true;
-                    }
-                    if state.timing.t0 {
-                        ins_adc(state);
-                    }
+            }
+            Instruct::NOP if op_code == 0x5C => {
+                if state.timing.t2 {
+                    state.ad = state.pd as u16;
+                } else if state.timing.t3 {
+                    state.ad |= (state.pd as u16) << 8;
+                } else if state.timing.t6 {
+                    state.next_timing.sd1 = true;
+                }
+            }
+            _ => {
+                if state.timing.t2 {
+                    state.ad = state.pd as u16;
+                } else if state.timing.t3 {
+                    state.ad |= (state.pd as u16) << 8;
+                } else {
+                    operand_step2(state, instruction);
                 }
-                _ => unimplemented!()
+            }
+        },
+        AddressType::AbsoluteX | AddressType::AbsoluteY => {
+            if state.timing.t2 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t3 {
+                let index = match mode {
+                    AddressType::AbsoluteX => state.registers.xr,
+                    _ => state.registers.yr,
+                };
+                let base = state.ad | ((state.pd as u16) << 8);
+                index_address(state, base, index);
+                page_penalty(state, info);
+            } else {
+                // without a page crossing the dummy read is already the operand
+                operand_step2(state, instruction);
+            }
+        }
+        AddressType::Indirect => {
+            if state.timing.t2 {
+                state.ptr = state.pd as u16;
+            } else if state.timing.t3 {
+                state.ptr |= (state.pd as u16) << 8;
+            } else if (state.timing.t4 && !state.variant.is_cmos()) || state.timing.t5 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t0 {
+                state.registers.pc = state.ad | ((state.pd as u16) << 8);
+            }
+        }
+        AddressType::IndirectX => {
+            if state.timing.t2 {
+                state.ptr = state.pd as u16;
+            } else if state.timing.t3 {
+                state.ptr = zero_page_index(state, state.ptr as u8, state.registers.xr);
+            } else if state.timing.t4 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t5 {
+                state.ad |= (state.pd as u16) << 8;
+            } else {
+                operand_step2(state, instruction);
+            }
+        }
+        AddressType::IndirectY => {
+            if state.timing.t2 {
+                state.ptr = state.pd as u16;
+            } else if state.timing.t3 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t4 {
+                let base = state.ad | ((state.pd as u16) << 8);
+                index_address(state, base, state.registers.yr);
+                page_penalty(state, info);
+            } else if !state.timing.t5 {
+                operand_step2(state, instruction);
+            }
+        }
+        AddressType::ZeroPageIndirect => {
+            if state.timing.t2 {
+                state.ptr = state.pd as u16;
+            } else if state.timing.t3 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t4 {
+                state.ad |= (state.pd as u16) << 8;
+            } else {
+                operand_step2(state, instruction);
+            }
+        }
+        AddressType::AbsoluteIndirectX => {
+            if state.timing.t2 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t3 {
+                let base = state.ad | ((state.pd as u16) << 8);
+                state.ptr = base.wrapping_add(state.registers.xr as u16);
+            } else if state.timing.t5 {
+                state.ad = state.pd as u16;
+            } else if state.timing.t0 {
+                state.registers.pc = state.ad | ((state.pd as u16) << 8);
             }
         }
-        _ => unimplemented!(),
     };
 
-    if state.timing.t1 {
-        let op_code = state.pd;
-        let next_instruct = Instruct::from_op_code(op_code).unwrap();
-        state.next_timing = TimingState::clear();
-        state.next_timing.t2 = true;
-        if next_instruct.cycles == 2 {
-            state.next_timing.t0 = true;
-        }
-    } else if !state.next_timing.t0 {
-        if state.timing.t0 {
-            state.next_timing.t1 = true;
-        } else if state.timing.t2 {
-            state.next_timing.t3 = true;
-        } else if state.timing.t3 {
-            state.next_timing.t4 = true;
-        } else if state.timing.t4 {
-            state.next_timing.t5 = true;
-        } else if state.timing.t5 {
-            state.next_timing.t6 = true;
-        } else if state.timing.t6 {
-            // if the timing is not set manually by the instruction by this point the processor
-            // will enter an infinite loop, maybe add a warning or loop detection here
+    if state.timing.t0 && decimal_cycle(state, instruction) {
+        state.cycles += 1;
+        state.next_timing.sd2 = true;
+    }
+    next_timing(state, instruction);
+    // interrupts are checked on the second to last cycle, BRK never checks them
+    if (state.next_timing.t0 || state.next_timing.sd2) && op_code != 0 {
+        poll_interrupts(state);
+    }
+}
+
+pub(crate) fn poll_interrupts(state: &mut State) {
+    state.interrupt = state.nmi_pending || (state.irq && !state.registers.status_has(INTERRUPT));
+}
+
+// selects the timing state for the next cycle, unless the instruction already did
+fn next_timing(state: &mut State, instruction: &Instruct) {
+    if !state.next_timing.is_clear() {
+        return;
+    }
+    let next = &mut state.next_timing;
+    let timing = &state.timing;
+    if timing.t0 || timing.sd2 {
+        next.t1 = true;
+        return;
+    } else if timing.sd1 {
+        next.sd2 = true;
+        return;
+    }
+    let cycle = if timing.t2 {
+        2
+    } else if timing.t3 {
+        3
+    } else if timing.t4 {
+        4
+    } else if timing.t5 {
+        5
+    } else {
+        6
+    };
+    // read-modify-write instructions end with a dummy write followed by the real one
+    if is_rmw(instruction) {
+        if cycle + 2 == state.cycles {
+            next.sd1 = true;
+            return;
         }
+    } else if cycle + 1 == state.cycles {
+        next.t0 = true;
+        return;
+    }
+    match cycle {
+        2 => next.t3 = true,
+        3 => next.t4 = true,
+        4 => next.t5 = true,
+        5 => next.t6 = true,
+        // if the timing is not set manually by the instruction by this point the processor
+        // will enter an infinite loop, maybe add a warning or loop detection here
+        _ => {}
     };
 }
 
-fn ins_adc(state: &mut State) -> () {
-    let value = state.pd;
-    if state.registers.status_has(CARRY) {
-        let (value, carry) = state.registers.ac.overflowing_add(1);
-        state.registers.ac = value;
-        if carry {
-            state.registers.status_add(CARRY)
-        } else {
-            state.registers.status_remove(CARRY)
+fn fetch(state: &mut State) {
+    state.ab = state.registers.pc;
+    state.registers.pc = state.registers.pc.wrapping_add(1);
+}
+
+fn write(state: &mut State, addr: u16, value: u8) {
+    state.ab = addr;
+    state.db = value;
+    state.rw = false;
+}
+
+// the stack always lives in page one, the pointer wraps around inside it
+fn stack_addr(state: &State) -> u16 {
+    0x0100 | state.registers.sp as u16
+}
+
+// the stack pointer is decremented on φ2 of the same cycle
+fn push(state: &mut State, value: u8) {
+    write(state, stack_addr(state), value);
+}
+
+// adds the index to the low byte only, the carry into the high byte takes another cycle
+// address of zp,X zp,Y and (zp,X)
+pub(crate) fn zero_page_index(state: &State, base: u8, index: u8) -> u16 {
+    if state.quirks().zero_page_wrap {
+        base.wrapping_add(index) as u16
+    } else {
+        base as u16 + index as u16
+    }
+}
+
+// address of the high byte of a zero page pointer
+pub(crate) fn pointer_high(state: &State, ptr: u16) -> u16 {
+    if state.quirks().pointer_wrap {
+        (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF)
+    } else {
+        ptr.wrapping_add(1)
+    }
+}
+
+// address of the high byte of the JMP ($xxxx) target
+pub(crate) fn indirect_high(state: &State, ptr: u16) -> u16 {
+    if state.quirks().indirect_jump_wrap {
+        // the high byte of the pointer is not incremented
+        (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF)
+    } else {
+        ptr.wrapping_add(1)
+    }
+}
+
+fn index_address(state: &mut State, base: u16, index: u8) {
+    state.ad = base.wrapping_add(index as u16);
+    state.ptr = (base & 0xFF00) | (state.ad & 0x00FF);
+}
+
+// the NMOS parts read from the unfixed address, the 65C02 reads the last operand byte again
+fn fixup_addr(state: &State) -> u16 {
+    if state.variant.is_cmos() && state.ptr != state.ad {
+        state.registers.pc.wrapping_sub(1)
+    } else {
+        state.ptr
+    }
+}
+
+// reads only take the extra cycle when the index crosses a page, writes always take it
+fn page_penalty(state: &mut State, info: &InstructionInfo) {
+    if info.extra_cycles > 0 && state.ptr != state.ad {
+        state.cycles += 1;
+    }
+}
+
+// bus access for the cycles after the effective address is known
+fn operand_step1(state: &mut State, instruction: &Instruct) {
+    if state.timing.t0 {
+        match instruction {
+            Instruct::STA => write(state, state.ad, state.registers.ac),
+            Instruct::STX => write(state, state.ad, state.registers.xr),
+            Instruct::STY => write(state, state.ad, state.registers.yr),
+            Instruct::STZ => write(state, state.ad, 0),
+            Instruct::SAX => write(state, state.ad, state.registers.ac & state.registers.xr),
+            Instruct::SHA | Instruct::SHX | Instruct::SHY | Instruct::TAS => {
+                let (addr, value) =
+                    unstable_store(&state.registers, instruction, state.ptr, state.ad);
+                write(state, addr, value);
+            }
+            _ => state.ab = state.ad,
         };
+    } else if state.timing.sd1 && state.variant.is_cmos() {
+        // the 65C02 reads the operand again instead of writing it back twice
+        state.ab = state.ad;
+    } else if state.timing.sd1 || state.timing.sd2 {
+        write(state, state.ad, state.alu);
+    } else {
+        state.ab = state.ad;
     }
-    let (result, carry) = state.registers.ac.overflowing_add(value);
-    state.registers.ac = result;
-    if carry {
-        state.registers.status_add(CARRY)
+}
+
+fn operand_step2(state: &mut State, instruction: &Instruct) {
+    if state.timing.t0 {
+        execute(state, instruction);
+    } else if state.timing.sd1 {
+        state.alu = modify(&mut state.registers, state.variant, instruction, state.alu);
+    } else if !state.timing.sd2 {
+        state.alu = state.pd;
+    }
+}
+
+// the 65C02 takes an extra cycle to fix the flags of ADC and SBC in decimal mode
+pub(crate) fn decimal_cycle(state: &State, instruction: &Instruct) -> bool {
+    state.variant.is_cmos()
+        && state.registers.status_has(DECIMAL)
+        && matches!(instruction, Instruct::ADC | Instruct::SBC)
+}
+
+pub(crate) fn is_rmw(instruction: &Instruct) -> bool {
+    matches!(
+        instruction,
+        Instruct::ASL
+            | Instruct::LSR
+            | Instruct::ROL
+            | Instruct::ROR
+            | Instruct::INC
+            | Instruct::DEC
+            | Instruct::SLO
+            | Instruct::RLA
+            | Instruct::SRE
+            | Instruct::RRA
+            | Instruct::DCP
+            | Instruct::ISC
+            | Instruct::TRB
+            | Instruct::TSB
+    )
+}
+
+// SHA, SHX, SHY and TAS store the register anded with the high byte of the base address + 1,
+// when the index crosses a page that value also ends up as the high byte of the address
+pub(crate) fn unstable_store(
+    registers: &Registers,
+    instruction: &Instruct,
+    unfixed: u16,
+    addr: u16,
+) -> (u16, u8) {
+    let register = match instruction {
+        Instruct::SHX => registers.xr,
+        Instruct::SHY => registers.yr,
+        _ => registers.ac & registers.xr,
     };
+    let value = register & ((unfixed >> 8) as u8).wrapping_add(1);
+    if unfixed != addr {
+        ((value as u16) << 8 | (addr & 0x00FF), value)
+    } else {
+        (addr, value)
+    }
+}
+
+pub(crate) fn branch_taken(registers: &Registers, instruction: &Instruct) -> bool {
+    match instruction {
+        Instruct::BCC => !registers.status_has(CARRY),
+        Instruct::BCS => registers.status_has(CARRY),
+        Instruct::BNE => !registers.status_has(ZERO),
+        Instruct::BEQ => registers.status_has(ZERO),
+        Instruct::BPL => !registers.status_has(NEGATIVE),
+        Instruct::BMI => registers.status_has(NEGATIVE),
+        Instruct::BVC => !registers.status_has(OVERFLOW),
+        Instruct::BVS => registers.status_has(OVERFLOW),
+        Instruct::BRA => true,
+        _ => false,
+    }
+}
+
+// executes instructions that only read their operand (from pd) or act on registers
+pub(crate) fn execute(state: &mut State, instruction: &Instruct) {
+    let value = state.pd;
+    let variant = state.variant;
+    let registers = &mut state.registers;
+    match instruction {
+        Instruct::ADC => ins_adc(registers, variant, value),
+        Instruct::SBC => ins_sbc(registers, variant, value),
+        Instruct::AND => {
+            registers.ac &= value;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::ORA => {
+            registers.ac |= value;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::EOR => {
+            registers.ac ^= value;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::LDA => {
+            registers.ac = value;
+            registers.set_nz(value);
+        }
+        Instruct::LDX => {
+            registers.xr = value;
+            registers.set_nz(value);
+        }
+        Instruct::LDY => {
+            registers.yr = value;
+            registers.set_nz(value);
+        }
+        Instruct::CMP => registers.compare(registers.ac, value),
+        Instruct::CPX => registers.compare(registers.xr, value),
+        Instruct::CPY => registers.compare(registers.yr, value),
+        Instruct::BIT => {
+            registers.status_set(ZERO, registers.ac & value == 0);
+            // the 65C02 immediate mode only sets Z
+            if state.ir != 0x89 {
+                registers.status_set(NEGATIVE, value & NEGATIVE != 0);
+                registers.status_set(OVERFLOW, value & OVERFLOW != 0);
+            }
+        }
+        Instruct::CLC => registers.status_remove(CARRY),
+        Instruct::CLD => registers.status_remove(DECIMAL),
+        Instruct::CLI => registers.status_remove(INTERRUPT),
+        Instruct::CLV => registers.status_remove(OVERFLOW),
+        Instruct::SEC => registers.status_add(CARRY),
+        Instruct::SED => registers.status_add(DECIMAL),
+        Instruct::SEI => registers.status_add(INTERRUPT),
+        Instruct::DEX => {
+            registers.xr = registers.xr.wrapping_sub(1);
+            registers.set_nz(registers.xr);
+        }
+        Instruct::DEY => {
+            registers.yr = registers.yr.wrapping_sub(1);
+            registers.set_nz(registers.yr);
+        }
+        Instruct::INX => {
+            registers.xr = registers.xr.wrapping_add(1);
+            registers.set_nz(registers.xr);
+        }
+        Instruct::INY => {
+            registers.yr = registers.yr.wrapping_add(1);
+            registers.set_nz(registers.yr);
+        }
+        Instruct::TAX => {
+            registers.xr = registers.ac;
+            registers.set_nz(registers.xr);
+        }
+        Instruct::TAY => {
+            registers.yr = registers.ac;
+            registers.set_nz(registers.yr);
+        }
+        Instruct::TSX => {
+            registers.xr = registers.sp;
+            registers.set_nz(registers.xr);
+        }
+        Instruct::TXA => {
+            registers.ac = registers.xr;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::TXS => registers.sp = registers.xr,
+        Instruct::TYA => {
+            registers.ac = registers.yr;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::LAX => {
+            registers.ac = value;
+            registers.xr = value;
+            registers.set_nz(value);
+        }
+        Instruct::LAS => {
+            let result = value & registers.sp;
+            registers.ac = result;
+            registers.xr = result;
+            registers.sp = result;
+            registers.set_nz(result);
+        }
+        Instruct::ANC => {
+            registers.ac &= value;
+            registers.set_nz(registers.ac);
+            registers.status_set(CARRY, registers.ac & 0x80 != 0);
+        }
+        Instruct::ALR => {
+            let result = registers.ac & value;
+            registers.ac = modify(registers, variant, &Instruct::LSR, result);
+        }
+        Instruct::ARR => ins_arr(registers, variant, value),
+        Instruct::SBX => {
+            let result = registers.ac & registers.xr;
+            registers.status_set(CARRY, result >= value);
+            registers.xr = result.wrapping_sub(value);
+            registers.set_nz(registers.xr);
+        }
+        // the constant or'ed into the accumulator differs between chips, 0xEE is the usual one
+        Instruct::ANE => {
+            registers.ac = (registers.ac | 0xEE) & registers.xr & value;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::LXA => {
+            registers.ac = (registers.ac | 0xEE) & value;
+            registers.xr = registers.ac;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::TAS => registers.sp = registers.ac & registers.xr,
+        Instruct::WAI => state.waiting = true,
+        // STP stops the clock until a reset, the same way a JAM does
+        Instruct::STP => {
+            state.fault = Some(Fault {
+                pc: state.registers.pc.wrapping_sub(1),
+                op_code: state.ir,
+            })
+        }
+        _ => {}
+    };
+}
+
+// computes the result of a read-modify-write instruction
+pub(crate) fn modify(
+    registers: &mut Registers,
+    variant: Variant,
+    instruction: &Instruct,
+    value: u8,
+) -> u8 {
+    let carry = registers.status_has(CARRY) as u8;
+    let result = match instruction {
+        // only Z is changed, from the bits the accumulator has in common with the operand
+        Instruct::TRB | Instruct::TSB => {
+            registers.status_set(ZERO, registers.ac & value == 0);
+            return match instruction {
+                Instruct::TRB => value & !registers.ac,
+                _ => value | registers.ac,
+            };
+        }
+        Instruct::ASL | Instruct::SLO => {
+            registers.status_set(CARRY, value & 0x80 != 0);
+            value << 1
+        }
+        Instruct::LSR | Instruct::SRE => {
+            registers.status_set(CARRY, value & 0x01 != 0);
+            value >> 1
+        }
+        Instruct::ROL | Instruct::RLA => {
+            registers.status_set(CARRY, value & 0x80 != 0);
+            (value << 1) | carry
+        }
+        Instruct::ROR | Instruct::RRA => {
+            registers.status_set(CARRY, value & 0x01 != 0);
+            (value >> 1) | (carry << 7)
+        }
+        Instruct::INC | Instruct::ISC => value.wrapping_add(1),
+        Instruct::DEC | Instruct::DCP => value.wrapping_sub(1),
+        _ => value,
+    };
+    registers.set_nz(result);
+    // the undocumented ones feed the result into a second operation on the accumulator
+    match instruction {
+        Instruct::SLO => {
+            registers.ac |= result;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::RLA => {
+            registers.ac &= result;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::SRE => {
+            registers.ac ^= result;
+            registers.set_nz(registers.ac);
+        }
+        Instruct::RRA => ins_adc(registers, variant, result),
+        Instruct::DCP => registers.compare(registers.ac, result),
+        Instruct::ISC => ins_sbc(registers, variant, result),
+        _ => {}
+    };
+    result
+}
+
+fn ins_adc(registers: &mut Registers, variant: Variant, value: u8) {
+    let ac = registers.ac as u16;
+    let value = value as u16;
+    let carry = registers.status_has(CARRY) as u16;
+
+    let binary = ac + value + carry;
+    // the zero flag always comes from the binary sum, even in decimal mode
+    registers.status_set(ZERO, binary & 0xFF == 0);
+    if !registers.status_has(DECIMAL) || !variant.has_decimal() {
+        registers.status_set(CARRY, binary > 0xFF);
+        registers.status_set(OVERFLOW, (!(ac ^ value) & (ac ^ binary) & 0x80) != 0);
+        registers.status_set(NEGATIVE, binary & 0x80 != 0);
+        registers.ac = binary as u8;
+        return;
+    }
+
+    let mut low = (ac & 0x0F) + (value & 0x0F) + carry;
+    if low >= 0x0A {
+        low = ((low + 0x06) & 0x0F) + 0x10;
+    }
+    let mut result = (ac & 0xF0) + (value & 0xF0) + low;
+    // N and V are taken before the high nibble is adjusted
+    registers.status_set(NEGATIVE, result & 0x80 != 0);
+    registers.status_set(OVERFLOW, (!(ac ^ value) & (ac ^ result) & 0x80) != 0);
+    if result >= 0xA0 {
+        result += 0x60;
+    }
+    registers.status_set(CARRY, result > 0xFF);
+    registers.ac = result as u8;
+    // the 65C02 fixed N and Z in decimal mode
+    if variant.is_cmos() {
+        registers.set_nz(registers.ac);
+    }
+}
+
+fn ins_sbc(registers: &mut Registers, variant: Variant, value: u8) {
+    let ac = registers.ac as i16;
+    let value = value as i16;
+    let borrow = 1 - registers.status_has(CARRY) as i16;
+
+    // all flags come from the binary subtraction, even in decimal mode
+    let binary = ac - value - borrow;
+    registers.status_set(CARRY, binary >= 0);
+    registers.status_set(OVERFLOW, ((ac ^ value) & (ac ^ binary) & 0x80) != 0);
+    registers.set_nz(binary as u8);
+    if !registers.status_has(DECIMAL) || !variant.has_decimal() {
+        registers.ac = binary as u8;
+        return;
+    }
+
+    let mut low = (ac & 0x0F) - (value & 0x0F) - borrow;
+    if variant.is_cmos() {
+        // the 65C02 adjusts the binary result and fixed N and Z
+        let mut result = binary;
+        if result < 0 {
+            result -= 0x60;
+        }
+        if low < 0 {
+            result -= 0x06;
+        }
+        registers.ac = result as u8;
+        registers.set_nz(registers.ac);
+        return;
+    }
+    if low < 0 {
+        low = ((low - 0x06) & 0x0F) - 0x10;
+    }
+    let mut result = (ac & 0xF0) - (value & 0xF0) + low;
+    if result < 0 {
+        result -= 0x60;
+    }
+    registers.ac = result as u8;
+}
+
+// AND followed by ROR, in decimal mode the adder fixes up the result like ADC does
+fn ins_arr(registers: &mut Registers, variant: Variant, value: u8) {
+    let and = registers.ac & value;
+    let carry = registers.status_has(CARRY) as u8;
+    let mut result = (and >> 1) | (carry << 7);
+    registers.set_nz(result);
+    registers.status_set(OVERFLOW, (result ^ (result << 1)) & 0x40 != 0);
+    if !registers.status_has(DECIMAL) || !variant.has_decimal() {
+        registers.status_set(CARRY, result & 0x40 != 0);
+        registers.ac = result;
+        return;
+    }
+
+    if (and & 0x0F) + (and & 0x01) > 0x05 {
+        result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
+    }
+    let carry = (and & 0xF0) as u16 + (and & 0x10) as u16 > 0x50;
+    if carry {
+        result = result.wrapping_add(0x60);
+    }
+    registers.status_set(CARRY, carry);
+    registers.ac = result;
 }
@@ -1,7 +1,7 @@
 use std::io;
 
 use rs6502::asm::{assemble, read_lines};
-use rs6502::m6502::{step, State};
+use rs6502::m6502::{run_instruction, State};
 use rs6502::memory::{DefaultMemory, Memory};
 
 fn main() -> io::Result<()> {
@@ -19,13 +19,7 @@ fn main() -> io::Result<()> {
         memory.set(key, val);
     }
 
-    step(&mut state);
-    if state.rw {
-        state.db = memory.get(state.ab);
-    } else {
-        memory.set(state.ab, state.db);
-    }
-    step(&mut state);
+    run_instruction(&mut state, &mut memory);
 
     println!("{state:?}");
     Ok(())
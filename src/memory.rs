@@ -2,10 +2,26 @@ use std::fmt;
 
 pub trait Memory: fmt::Debug {
     fn get(&self, addr: u16) -> u8;
-    fn set(&mut self, addr: u16, value: u8) -> ();
+    fn set(&mut self, addr: u16, value: u8);
 }
 
-#[derive(Debug)]
+// what the processor sees on the other side of the address and data bus, reads take
+// `&mut self` so devices can react to them (e.g. clearing a flag when a register is read)
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, value: u8);
+}
+
+impl<T: Memory> Bus for T {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.get(addr)
+    }
+    fn write(&mut self, addr: u16, value: u8) {
+        self.set(addr, value);
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct DefaultMemory {
     memory: [u8; 65536],
 }
@@ -16,11 +32,17 @@ impl DefaultMemory {
     }
 }
 
+impl Default for DefaultMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Memory for DefaultMemory {
     fn get(&self, addr: u16) -> u8 {
         return self.memory[addr as usize];
     }
-    fn set(&mut self, addr: u16, value: u8) -> () {
+    fn set(&mut self, addr: u16, value: u8) {
         self.memory[addr as usize] = value;
     }
 }
@@ -0,0 +1,75 @@
+// Runs a program headless until it stops, for tests written in assembly.
+//
+// The conditions are checked between instructions, on the instruction level executor. A
+// program usually ends in a BRK or in a `jmp *` trap, the cycle limit catches the rest.
+
+use std::fmt;
+
+use crate::fast::run_instruction;
+use crate::m6502::{Fault, State};
+use crate::memory::Memory;
+
+const BRK: u8 = 0x00;
+
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    // stops before the instruction at this address runs
+    pub until: Option<u16>,
+    pub max_cycles: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    // a BRK is about to run at the address
+    Brk(u16),
+    // the instruction at the address jumps or branches to itself
+    Trap(u16),
+    // the `until` address was reached
+    Reached(u16),
+    CycleLimit,
+    Jammed(Fault),
+    // waiting for an interrupt that nothing will send
+    Waiting,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stop::Brk(pc) => write!(f, "brk at {pc:04x}"),
+            Stop::Trap(pc) => write!(f, "trapped at {pc:04x}"),
+            Stop::Reached(pc) => write!(f, "reached {pc:04x}"),
+            Stop::CycleLimit => write!(f, "cycle limit"),
+            Stop::Jammed(fault) => write!(f, "{fault}"),
+            Stop::Waiting => write!(f, "waiting for an interrupt"),
+        }
+    }
+}
+
+pub fn run_until<M: Memory>(state: &mut State, memory: &mut M, limits: &Limits) -> Stop {
+    let end = limits.max_cycles.map(|cycles| state.total_cycles + cycles);
+    loop {
+        if let Some(fault) = state.fault {
+            return Stop::Jammed(fault);
+        }
+        // the reset or interrupt sequence comes before the instruction at pc
+        let pending = state.resetting || state.interrupt;
+        let pc = state.registers.pc;
+        if !pending {
+            if limits.until == Some(pc) {
+                return Stop::Reached(pc);
+            }
+            if memory.get(pc) == BRK {
+                return Stop::Brk(pc);
+            }
+        }
+        if end.is_some_and(|end| state.total_cycles >= end) {
+            return Stop::CycleLimit;
+        }
+        if run_instruction(state, memory) == 0 {
+            return Stop::Waiting;
+        }
+        if !pending && state.fault.is_none() && state.registers.pc == pc {
+            return Stop::Trap(pc);
+        }
+    }
+}
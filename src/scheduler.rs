@@ -0,0 +1,139 @@
+// Master clock and timed events for the devices around the processor.
+//
+// The clock counts every cycle of the machine, it keeps going while the processor is held
+// in reset, jammed or waiting for an interrupt. Devices schedule their own event type,
+// which is handed back to them on the cycle it is due, before the processor runs it.
+
+use std::collections::BTreeMap;
+
+use crate::fast;
+use crate::m6502::{cycle, State};
+use crate::memory::Bus;
+
+// identifies a scheduled event so it can be cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EventId {
+    time: u64,
+    // events due on the same cycle fire in the order they were scheduled
+    seq: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scheduler<E> {
+    now: u64,
+    seq: u64,
+    events: BTreeMap<EventId, E>,
+}
+
+impl<E> Default for Scheduler<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Scheduler<E> {
+    pub fn new() -> Scheduler<E> {
+        Scheduler {
+            now: 0,
+            seq: 0,
+            events: BTreeMap::new(),
+        }
+    }
+
+    // cycles since the machine was created
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // fires the event `delay` cycles from now
+    pub fn schedule(&mut self, delay: u64, event: E) -> EventId {
+        self.schedule_at(self.now + delay, event)
+    }
+
+    // fires the event on the given cycle, right away if it already passed
+    pub fn schedule_at(&mut self, time: u64, event: E) -> EventId {
+        let id = EventId {
+            time,
+            seq: self.seq,
+        };
+        self.seq += 1;
+        self.events.insert(id, event);
+        id
+    }
+
+    pub fn cancel(&mut self, id: EventId) -> Option<E> {
+        self.events.remove(&id)
+    }
+
+    // the cycle the next event is due
+    pub fn next_time(&self) -> Option<u64> {
+        self.events.keys().next().map(|id| id.time)
+    }
+
+    // takes the next event that is due, with the cycle it was scheduled for
+    pub fn pop_due(&mut self) -> Option<(u64, E)> {
+        if self.next_time()? > self.now {
+            return None;
+        }
+        self.events.pop_first().map(|(id, event)| (id.time, event))
+    }
+
+    pub fn advance(&mut self, cycles: u64) {
+        self.now += cycles;
+    }
+
+    // hands every due event to `handle`, which can schedule new ones
+    fn fire<B: Bus + ?Sized>(
+        &mut self,
+        state: &mut State,
+        bus: &mut B,
+        handle: &mut impl FnMut(E, &mut State, &mut B, &mut Scheduler<E>),
+    ) {
+        while let Some((_, event)) = self.pop_due() {
+            handle(event, state, bus, self);
+        }
+    }
+
+    // runs the cycle level core for the given number of cycles, the events fire on their
+    // exact cycle
+    pub fn run<B: Bus + ?Sized>(
+        &mut self,
+        state: &mut State,
+        bus: &mut B,
+        cycles: u64,
+        mut handle: impl FnMut(E, &mut State, &mut B, &mut Scheduler<E>),
+    ) {
+        let end = self.now + cycles;
+        while self.now < end {
+            self.fire(state, bus, &mut handle);
+            cycle(state, bus);
+            self.advance(1);
+        }
+    }
+
+    // runs whole instructions until at least the given number of cycles passed, the events
+    // fire on the first instruction boundary at or after their cycle
+    pub fn run_fast<B: Bus + ?Sized>(
+        &mut self,
+        state: &mut State,
+        bus: &mut B,
+        cycles: u64,
+        mut handle: impl FnMut(E, &mut State, &mut B, &mut Scheduler<E>),
+    ) {
+        let end = self.now + cycles;
+        while self.now < end {
+            self.fire(state, bus, &mut handle);
+            // a held or stopped processor still lets the clock run
+            let taken = fast::run_instruction(state, bus).max(1);
+            self.advance(taken as u64);
+        }
+    }
+}
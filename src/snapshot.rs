@@ -0,0 +1,262 @@
+// Machine snapshots, the processor state and the whole address space in one file.
+//
+// The file starts with a magic string and a version number, followed by the fields in a
+// fixed order, all numbers little endian. Loading only accepts versions it knows, so a
+// file written by a newer build fails instead of restoring a half broken machine.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::instruct::{Quirks, Variant};
+use crate::m6502::{Fault, IoPort, Registers, State, TimingState};
+use crate::memory::{DefaultMemory, Memory};
+
+const MAGIC: &[u8; 8] = b"RS6502SN";
+pub const VERSION: u16 = 3;
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub state: State,
+    pub memory: DefaultMemory,
+}
+
+impl Snapshot {
+    pub fn new(state: &State, memory: &dyn Memory) -> Snapshot {
+        let mut copy = DefaultMemory::new();
+        for addr in 0..=0xFFFF {
+            copy.set(addr, memory.get(addr));
+        }
+        Snapshot {
+            state: state.clone(),
+            memory: copy,
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(&mut fs::File::create(path)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+        Snapshot::read(&mut fs::File::open(path)?)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut out = Vec::with_capacity(0x10100);
+        out.extend(MAGIC);
+        out.extend(VERSION.to_le_bytes());
+        write_state(&mut out, &self.state);
+        for addr in 0..=0xFFFF {
+            out.push(self.memory.get(addr));
+        }
+        writer.write_all(&out)
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Snapshot> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut input = Input {
+            data: &data,
+            pos: 0,
+        };
+
+        if input.bytes(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a snapshot file"));
+        }
+        let version = input.u16()?;
+        if version == 0 || version > VERSION {
+            return Err(invalid(&format!("unsupported snapshot version {version}")));
+        }
+        let state = read_state(&mut input, version)?;
+        let mut memory = DefaultMemory::new();
+        for (addr, value) in input.bytes(0x10000)?.iter().enumerate() {
+            memory.set(addr as u16, *value);
+        }
+        if input.pos != data.len() {
+            return Err(invalid("trailing data after the memory"));
+        }
+        Ok(Snapshot { state, memory })
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+fn write_state(out: &mut Vec<u8>, state: &State) {
+    let registers = &state.registers;
+    out.extend(registers.pc.to_le_bytes());
+    out.extend([
+        registers.ac,
+        registers.xr,
+        registers.yr,
+        registers.sr,
+        registers.sp,
+    ]);
+    out.extend(state.total_cycles.to_le_bytes());
+
+    out.extend(state.ab.to_le_bytes());
+    out.extend([state.db, state.rw as u8, state.ir, state.pd]);
+    out.extend(state.ad.to_le_bytes());
+    out.extend(state.ptr.to_le_bytes());
+    out.extend([state.alu, state.cycles]);
+
+    out.extend([state.clock1 as u8, state.clock2 as u8]);
+    out.extend(state.timing.to_bits().to_le_bytes());
+    out.extend(state.next_timing.to_bits().to_le_bytes());
+
+    let pins = [
+        state.sync,
+        state.res,
+        state.irq,
+        state.nmi,
+        state.rdy,
+        state.so,
+        state.so_prev,
+        state.stalled,
+        state.nmi_prev,
+        state.nmi_pending,
+        state.interrupt,
+        state.resetting,
+    ];
+    out.extend(pins.map(|pin| pin as u8));
+
+    match state.fault {
+        Some(fault) => {
+            out.push(1);
+            out.extend(fault.pc.to_le_bytes());
+            out.push(fault.op_code);
+        }
+        None => out.extend([0, 0, 0, 0]),
+    }
+    out.push(state.undocumented as u8);
+    out.push(
+        Variant::ALL
+            .iter()
+            .position(|v| *v == state.variant)
+            .unwrap() as u8,
+    );
+    match state.quirks {
+        Some(quirks) => out.extend([
+            1,
+            quirks.indirect_jump_wrap as u8,
+            quirks.zero_page_wrap as u8,
+            quirks.pointer_wrap as u8,
+        ]),
+        None => out.extend([0, 0, 0, 0]),
+    }
+    out.push(state.waiting as u8);
+    out.extend([state.port.direction, state.port.output, state.port.input]);
+    out.extend(state.half_cycles.to_le_bytes());
+}
+
+fn read_state(input: &mut Input, version: u16) -> io::Result<State> {
+    let mut state = State::new();
+    state.registers = Registers {
+        pc: input.u16()?,
+        ac: input.u8()?,
+        xr: input.u8()?,
+        yr: input.u8()?,
+        sr: input.u8()?,
+        sp: input.u8()?,
+    };
+    // version 1 had a 32 bit counter
+    state.total_cycles = if version == 1 {
+        let cycles = i32::from_le_bytes(input.bytes(4)?.try_into().unwrap());
+        u64::try_from(cycles).map_err(|_| invalid("negative cycle count"))?
+    } else {
+        u64::from_le_bytes(input.bytes(8)?.try_into().unwrap())
+    };
+
+    state.ab = input.u16()?;
+    state.db = input.u8()?;
+    state.rw = input.bool()?;
+    state.ir = input.u8()?;
+    state.pd = input.u8()?;
+    state.ad = input.u16()?;
+    state.ptr = input.u16()?;
+    state.alu = input.u8()?;
+    state.cycles = input.u8()?;
+
+    state.clock1 = input.bool()?;
+    state.clock2 = input.bool()?;
+    state.timing = TimingState::from_bits(input.u16()?);
+    state.next_timing = TimingState::from_bits(input.u16()?);
+
+    state.sync = input.bool()?;
+    state.res = input.bool()?;
+    state.irq = input.bool()?;
+    state.nmi = input.bool()?;
+    state.rdy = input.bool()?;
+    state.so = input.bool()?;
+    state.so_prev = input.bool()?;
+    state.stalled = input.bool()?;
+    state.nmi_prev = input.bool()?;
+    state.nmi_pending = input.bool()?;
+    state.interrupt = input.bool()?;
+    state.resetting = input.bool()?;
+
+    let jammed = input.bool()?;
+    let fault = Fault {
+        pc: input.u16()?,
+        op_code: input.u8()?,
+    };
+    state.fault = jammed.then_some(fault);
+    state.undocumented = input.bool()?;
+    state.variant = *Variant::ALL
+        .get(input.u8()? as usize)
+        .ok_or_else(|| invalid("unknown cpu variant"))?;
+    let replaced = input.bool()?;
+    let quirks = Quirks {
+        indirect_jump_wrap: input.bool()?,
+        zero_page_wrap: input.bool()?,
+        pointer_wrap: input.bool()?,
+    };
+    state.quirks = replaced.then_some(quirks);
+    state.waiting = input.bool()?;
+    state.port = IoPort {
+        direction: input.u8()?,
+        output: input.u8()?,
+        input: input.u8()?,
+    };
+    // before version 3 the clock wasn't counted separately
+    state.half_cycles = if version < 3 {
+        (state.total_cycles * 2).saturating_sub(state.clock2 as u64)
+    } else {
+        u64::from_le_bytes(input.bytes(8)?.try_into().unwrap())
+    };
+    Ok(state)
+}
+
+struct Input<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Input<'_> {
+    fn bytes(&mut self, len: usize) -> io::Result<&[u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("snapshot is truncated"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("invalid flag")),
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rs6502::asm::lexer::lex;
+use rs6502::asm::parser::{parse, parse_with};
+use rs6502::asm::source::Sources;
+use rs6502::asm::AsmError;
+use rs6502::instruct::Variant;
+
+fn assemble(program: &[&str]) -> Result<BTreeMap<u16, u8>, AsmError> {
+    let lines: Vec<String> = program.iter().map(|l| l.to_string()).collect();
+    parse(lex(lines.iter())?, Variant::Nmos6502)
+}
+
+// the bytes of a program assembled at $0600
+fn bytes(program: &[&str]) -> Vec<u8> {
+    let result = assemble(program).unwrap_or_else(|e| panic!("{}: {:?}", e.reason, e.symbol));
+    assert_eq!(result.keys().next(), Some(&0x0600));
+    result.into_values().collect()
+}
+
+fn error(program: &[&str]) -> String {
+    assemble(program).unwrap_err().reason
+}
+
+// a fresh directory with the given files in it
+fn files(test: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rs6502-{test}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (name, contents) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+// assembles `main.asm` in `dir`, the sources are returned to look the errors up in
+fn assemble_in(
+    dir: &Path,
+    include_paths: &[PathBuf],
+) -> (Result<BTreeMap<u16, u8>, AsmError>, Sources) {
+    let path = dir.join("main.asm");
+    let text = fs::read_to_string(&path).unwrap();
+    let mut sources = Sources::new(include_paths.to_vec());
+    let file = sources.add(
+        String::from("main.asm"),
+        Some(path),
+        text.lines().map(|l| l.to_string()).collect(),
+    );
+    let result = sources
+        .tokens(file)
+        .and_then(|tokens| parse_with(tokens, Variant::Nmos6502, &mut sources));
+    (result, sources)
+}
+
+#[test]
+fn expressions() {
+    assert_eq!(bytes(&["lda #2+3*4"]), [0xA9, 14]);
+    assert_eq!(bytes(&["lda #(2+3)*4"]), [0xA9, 20]);
+    assert_eq!(bytes(&["lda #20-4-2"]), [0xA9, 14]);
+    assert_eq!(bytes(&["lda #100/7%4"]), [0xA9, 2]);
+    assert_eq!(bytes(&["lda #1<<3|1"]), [0xA9, 9]);
+    assert_eq!(bytes(&["lda #$F0>>4&$0E^1"]), [0xA9, 0x0F]);
+    assert_eq!(bytes(&["lda #%0101 | %1000"]), [0xA9, 0x0D]);
+    assert_eq!(bytes(&["lda #7 % 4"]), [0xA9, 3]);
+    assert_eq!(
+        bytes(&["lda #<$1234", "ldx #>$1234"]),
+        [0xA9, 0x34, 0xA2, 0x12]
+    );
+    assert_eq!(bytes(&["lda #'A'+1"]), [0xA9, 0x42]);
+    assert_eq!(bytes(&["lda #-1"]), [0xA9, 0xFF]);
+    assert_eq!(bytes(&["lda $10+$10"]), [0xA5, 0x20]);
+    assert_eq!(bytes(&["lda $0010"]), [0xAD, 0x10, 0x00]);
+    assert_eq!(bytes(&["lda $FF+1"]), [0xAD, 0x00, 0x01]);
+}
+
+#[test]
+fn labels_and_the_current_address() {
+    let program = [
+        "lda #<table",
+        "ldx #>table",
+        "sta table+1,x",
+        "jmp *",
+        "bne *-2",
+        "table:",
+    ];
+    assert_eq!(
+        bytes(&program),
+        [
+            0xA9, 0x0C, //
+            0xA2, 0x06, //
+            0x9D, 0x0D, 0x06, //
+            0x4C, 0x07, 0x06, //
+            0xD0, 0xFC,
+        ]
+    );
+}
+
+#[test]
+fn parentheses_and_indirect_addressing() {
+    assert_eq!(bytes(&["lda (2+3)*4,x"]), [0xB5, 20]);
+    assert_eq!(bytes(&["lda ($08+$08),y"]), [0xB1, 0x10]);
+    assert_eq!(bytes(&["lda ($10,x)"]), [0xA1, 0x10]);
+    assert_eq!(bytes(&["jmp ($1000+2)"]), [0x6C, 0x02, 0x10]);
+    assert_eq!(bytes(&["jmp ((1+1)*$800)"]), [0x6C, 0x00, 0x10]);
+    assert_eq!(
+        error(&["lda ($10),x"]),
+        "Invalid addressing mode, use ($nn,x) or ($nn),y"
+    );
+}
+
+#[test]
+fn expression_errors() {
+    assert_eq!(error(&["lda #256"]), "Value 256 doesnt fit in Immediate");
+    assert_eq!(error(&["lda #1/0"]), "Invalid operation 1 / 0");
+    assert_eq!(error(&["lda #<missing"]), "Undefined label: missing");
+    assert_eq!(error(&["lda #(1+2"]), "Early end of expression");
+    assert_eq!(error(&["lda #1 2"]), "Unexpected 2 in expression");
+}
+
+#[test]
+fn labels_in_every_addressing_mode() {
+    let program = [
+        "define constant ptr",
+        "define vector_low <vector",
+        ".ORG $0010",
+        "ptr:",
+        ".BYTES $00 $00",
+        ".ORG $0600",
+        "lda #constant",
+        "lda #vector_low",
+        "lda ptr",
+        "lda ptr,x",
+        "ldx ptr,y",
+        "lda table",
+        "lda table,x",
+        "lda table,y",
+        "lda (ptr,x)",
+        "sta (ptr),y",
+        "jmp (vector)",
+        "asl a",
+        "loop:",
+        "bne loop",
+        "table:",
+        "vector:",
+    ];
+    let result = assemble(&program).unwrap();
+    let code: Vec<u8> = result.range(0x0600..).map(|(_, b)| *b).collect();
+    assert_eq!(
+        code,
+        [
+            0xA9, 0x10, //
+            0xA9, 0x1D, //
+            0xA5, 0x10, //
+            0xB5, 0x10, //
+            0xB6, 0x10, //
+            0xAD, 0x1D, 0x06, //
+            0xBD, 0x1D, 0x06, //
+            0xB9, 0x1D, 0x06, //
+            0xA1, 0x10, //
+            0x91, 0x10, //
+            0x6C, 0x1D, 0x06, //
+            0x0A, //
+            0xD0, 0xFE,
+        ]
+    );
+}
+
+#[test]
+fn forward_references_are_sized_from_their_address() {
+    let program = [
+        "lda later",
+        "sta later,x",
+        "ldx table,y",
+        "jmp table",
+        ".ORG $0020",
+        "later:",
+        ".ORG $0700",
+        "table:",
+    ];
+    assert_eq!(
+        bytes(&program),
+        [
+            0xA5, 0x20, //
+            0x95, 0x20, //
+            0xBE, 0x00, 0x07, //
+            0x4C, 0x00, 0x07,
+        ]
+    );
+    // shrinking the first instruction brings the second label into the zero page
+    let program = [
+        ".ORG $00FA",
+        "lda zp",
+        "lda second",
+        "second:",
+        ".ORG $0010",
+        "zp:",
+    ];
+    let result = assemble(&program).unwrap();
+    assert_eq!(
+        result.into_values().collect::<Vec<u8>>(),
+        [0xA5, 0x10, 0xA5, 0xFE]
+    );
+}
+
+#[test]
+fn size_prefixes() {
+    assert_eq!(bytes(&["lda a:$10"]), [0xAD, 0x10, 0x00]);
+    assert_eq!(
+        bytes(&["lda a:zp,x", ".ORG $0010", "zp:"])[..3],
+        [0xBD, 0x10, 0x00]
+    );
+    assert_eq!(bytes(&["lda z:$0010"]), [0xA5, 0x10]);
+    assert_eq!(error(&["lda z:$1234"]), "Value 4660 doesnt fit in ZeroPage");
+    assert_eq!(
+        error(&["jmp z:$10"]),
+        "Invalid addres type for instruction jmp"
+    );
+    assert_eq!(
+        error(&["jmp a:($10)"]),
+        "Size prefix on an operand that has no zero page form"
+    );
+}
+
+#[test]
+fn phase_errors() {
+    // the operand is in the zero page only while the instruction is absolute
+    let program = ["lda $0702-end", "end:"];
+    let phase = assemble(&program).unwrap_err();
+    assert_eq!(
+        phase.reason,
+        "Phase error, the label addresses never settle"
+    );
+    assert_eq!(phase.symbol.unwrap().text, "end");
+
+    assert_eq!(error(&["twice:", "twice:"]), "Label twice defined twice");
+}
+
+#[test]
+fn data_directives() {
+    let program = [
+        ".byte 1, $FF, -1, <table, 'A', \"hi\"",
+        ".word table, $1234, -2",
+        ".dword $12345678",
+        ".text \"a\\tb\\n\\\\\\\"\\x7F\", 0",
+        ".asciiz \"ok\"",
+        "table:",
+    ];
+    assert_eq!(
+        bytes(&program),
+        [
+            0x01, 0xFF, 0xFF, 0x1C, 0x41, b'h', b'i', //
+            0x1C, 0x06, 0x34, 0x12, 0xFE, 0xFF, //
+            0x78, 0x56, 0x34, 0x12, //
+            b'a', b'\t', b'b', b'\n', b'\\', b'"', 0x7F, 0x00, //
+            b'o', b'k', 0x00,
+        ]
+    );
+    // vectors can use labels that come later
+    let program = [".ORG $FFFC", ".word start", ".ORG $0800", "start:"];
+    let result = assemble(&program).unwrap();
+    assert_eq!(result.get(&0xFFFC), Some(&0x00));
+    assert_eq!(result.get(&0xFFFD), Some(&0x08));
+}
+
+#[test]
+fn reservation_and_alignment() {
+    let program = [
+        ".byte 1",
+        ".res 2",
+        ".byte 2",
+        ".fill 3, $EA",
+        ".fill 1",
+        ".align 4",
+        "aligned:",
+        ".byte 3",
+        ".align $10, $FF",
+        "page:",
+        ".res page-aligned, 7",
+    ];
+    let result = assemble(&program).unwrap();
+    let layout: Vec<(u16, u8)> = result.into_iter().collect();
+    assert_eq!(
+        layout,
+        [
+            (0x0600, 1),
+            (0x0603, 2),
+            (0x0604, 0xEA),
+            (0x0605, 0xEA),
+            (0x0606, 0xEA),
+            (0x0607, 0),
+            (0x0608, 3),
+            (0x0609, 0xFF),
+            (0x060A, 0xFF),
+            (0x060B, 0xFF),
+            (0x060C, 0xFF),
+            (0x060D, 0xFF),
+            (0x060E, 0xFF),
+            (0x060F, 0xFF),
+            (0x0610, 7),
+            (0x0611, 7),
+            (0x0612, 7),
+            (0x0613, 7),
+            (0x0614, 7),
+            (0x0615, 7),
+            (0x0616, 7),
+            (0x0617, 7),
+        ]
+    );
+}
+
+#[test]
+fn data_errors() {
+    assert_eq!(error(&[".byte 256"]), "Value 256 doesnt fit in 1 bytes");
+    assert_eq!(
+        error(&[".word $10000"]),
+        "Value 65536 doesnt fit in 2 bytes"
+    );
+    assert_eq!(
+        error(&[".word \"no\""]),
+        "Strings only go in .byte, .text and .asciiz"
+    );
+    assert_eq!(error(&[".res later", "later:"]), "Undefined label: later");
+    assert_eq!(
+        error(&[".fill 1, 2, 3"]),
+        "Expected a count and an optional fill value"
+    );
+}
+
+#[test]
+fn includes() {
+    let dir = files(
+        "includes",
+        &[
+            (
+                "main.asm",
+                b"define ZERO 0\n.include \"lib/macros.asm\"\nlda #ONE\njsr init\n.include \"shared.asm\"\n",
+            ),
+            ("lib/macros.asm", b"define ONE 1\n.INCLUDE \"init.asm\"\n"),
+            // found next to macros.asm, which includes it
+            ("lib/init.asm", b"; nothing before the label\ninit:\nldx #ZERO\n"),
+            ("other/shared.asm", b"rts\n"),
+        ],
+    );
+    let (result, _) = assemble_in(&dir, &[dir.join("other")]);
+    assert_eq!(
+        result.unwrap().into_values().collect::<Vec<u8>>(),
+        [0xA2, 0x00, 0xA9, 0x01, 0x20, 0x00, 0x06, 0x60]
+    );
+
+    // without the include path shared.asm is not found
+    let (result, _) = assemble_in(&dir, &[]);
+    let error = result.unwrap_err();
+    assert_eq!(error.reason, "Cannot find shared.asm");
+    assert_eq!(error.symbol.unwrap().start.line, 5);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn include_errors_point_into_the_included_file() {
+    let dir = files(
+        "include-errors",
+        &[
+            ("main.asm", b"nop\n.include \"bad.asm\"\n"),
+            ("bad.asm", b"nop\n\n  lda #300\n"),
+        ],
+    );
+    let (result, sources) = assemble_in(&dir, &[]);
+    let error = result.unwrap_err();
+    let symbol = error.symbol.unwrap();
+    assert_eq!(error.reason, "Value 300 doesnt fit in Immediate");
+    assert!(sources.name(symbol.start.file).ends_with("bad.asm"));
+    assert_eq!((symbol.start.line, symbol.start.col), (3, 8));
+    assert_eq!(sources.line(&symbol.start), Some("  lda #300"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lexer_errors_point_into_the_included_file() {
+    assert_eq!(error(&["lda #`"]), "Invalid char '`'");
+    assert_eq!(error(&["lda #'ab'"]), "Invalid char literal");
+    assert_eq!(error(&["lda #\"ab"]), "Unterminated literal");
+
+    let dir = files(
+        "lexer-errors",
+        &[
+            ("main.asm", b"nop\n.include \"bad.asm\"\n"),
+            ("bad.asm", b"nop\n  lda #`\n"),
+        ],
+    );
+    let (result, sources) = assemble_in(&dir, &[]);
+    let error = result.unwrap_err();
+    let symbol = error.symbol.unwrap();
+    assert_eq!(error.reason, "Invalid char '`'");
+    assert!(sources.name(symbol.start.file).ends_with("bad.asm"));
+    assert_eq!((symbol.start.line, symbol.start.col), (2, 8));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn include_cycles() {
+    let dir = files(
+        "include-cycles",
+        &[
+            ("main.asm", b".include \"a.asm\"\n"),
+            ("a.asm", b".include \"b.asm\"\n"),
+            ("b.asm", b"nop\n.include \"a.asm\"\n"),
+        ],
+    );
+    let (result, sources) = assemble_in(&dir, &[]);
+    let error = result.unwrap_err();
+    assert_eq!(error.reason, "Include cycle: a.asm -> b.asm -> a.asm");
+    let symbol = error.symbol.unwrap();
+    assert!(sources.name(symbol.start.file).ends_with("b.asm"));
+    assert_eq!(symbol.start.line, 2);
+
+    fs::write(dir.join("main.asm"), ".include \"main.asm\"\n").unwrap();
+    let (result, _) = assemble_in(&dir, &[]);
+    assert_eq!(
+        result.unwrap_err().reason,
+        "Include cycle: main.asm -> main.asm"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn binary_includes() {
+    let dir = files(
+        "binary-includes",
+        &[
+            (
+                "main.asm",
+                b".incbin \"data.bin\"\n.incbin \"data.bin\", 2\n.incbin \"data.bin\", 1, 2\nafter:\n.word after\n",
+            ),
+            ("data.bin", &[1, 2, 3, 4]),
+        ],
+    );
+    let (result, _) = assemble_in(&dir, &[]);
+    assert_eq!(
+        result.unwrap().into_values().collect::<Vec<u8>>(),
+        [1, 2, 3, 4, 3, 4, 2, 3, 0x08, 0x06]
+    );
+
+    let errors = [
+        (
+            ".incbin \"data.bin\", 5",
+            "Offset 5 is past the end of the 4 byte file",
+        ),
+        (
+            ".incbin \"data.bin\", 2, 3",
+            "3 bytes from offset 2 is past the end of the 4 byte file",
+        ),
+        (
+            ".incbin \"data.bin\", later\nlater:",
+            "Undefined label: later",
+        ),
+        (
+            ".incbin data.bin",
+            "Expected a file name, an optional offset and an optional length",
+        ),
+        (".incbin \"missing.bin\"", "Cannot find missing.bin"),
+    ];
+    for (program, reason) in errors {
+        fs::write(dir.join("main.asm"), program).unwrap();
+        assert_eq!(assemble_in(&dir, &[]).0.unwrap_err().reason, reason);
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_command_line_reports_errors() {
+    let dir = files("cli", &[("main.asm", b".ORG $0600\nlda #$01\nbogus $10")]);
+    let asm = |path: PathBuf| {
+        Command::new(env!("CARGO_BIN_EXE_asm"))
+            .arg(path)
+            .output()
+            .unwrap()
+    };
+
+    let output = asm(dir.join("main.asm"));
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("ERROR: "), "{stderr}");
+    assert!(stderr.contains("main.asm:3:"), "{stderr}");
+
+    let output = asm(dir.join("missing.asm"));
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.asm"), "{stderr}");
+    fs::remove_dir_all(dir).unwrap();
+}
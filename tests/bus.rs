@@ -0,0 +1,75 @@
+// The processor drives the bus itself, devices see every access as it happens.
+
+mod common;
+
+use common::load;
+use rs6502::fast;
+use rs6502::m6502::{self, State};
+use rs6502::memory::{Bus, DefaultMemory, Memory};
+
+// RAM with a register at $D000 that counts its reads and keeps the writes to it
+struct Device {
+    memory: DefaultMemory,
+    reads: u8,
+    writes: Vec<u8>,
+}
+
+impl Bus for Device {
+    fn read(&mut self, addr: u16) -> u8 {
+        if addr == 0xD000 {
+            self.reads += 1;
+            return self.reads;
+        }
+        self.memory.get(addr)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr == 0xD000 {
+            self.writes.push(value);
+        } else {
+            self.memory.set(addr, value);
+        }
+    }
+}
+
+fn device(program: &[u8]) -> Device {
+    Device {
+        memory: load(program),
+        reads: 0,
+        writes: vec![],
+    }
+}
+
+const PROGRAM: [u8; 9] = [
+    0xAD, 0x00, 0xD0, // lda $D000
+    0x8D, 0x00, 0xD0, // sta $D000
+    0xEE, 0x00, 0xD0, // inc $D000
+];
+
+#[test]
+fn cycle_core_reaches_devices() {
+    let mut bus = device(&PROGRAM);
+    let mut state = State::new();
+    // through a trait object, the way a machine with several devices would pass it
+    let dyn_bus: &mut dyn Bus = &mut bus;
+    for _ in 0..4 {
+        m6502::run_instruction(&mut state, dyn_bus);
+    }
+    // the read-modify-write writes the value it read back before the result
+    assert_eq!(bus.reads, 2);
+    assert_eq!(bus.writes, [1, 2, 3]);
+    assert_eq!(state.registers.ac, 1);
+}
+
+#[test]
+fn fast_core_reaches_devices() {
+    let mut bus = device(&PROGRAM);
+    let mut state = State::new();
+    for _ in 0..4 {
+        fast::run_instruction(&mut state, &mut bus);
+    }
+    // the dummy write is skipped
+    assert_eq!(bus.reads, 2);
+    assert_eq!(bus.writes, [1, 3]);
+    assert_eq!(state.registers.ac, 1);
+}
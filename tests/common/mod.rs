@@ -0,0 +1,72 @@
+// Fixtures shared by the integration tests, each test crate only uses some of them.
+#![allow(dead_code)]
+
+use rs6502::fast;
+use rs6502::m6502::{self, State};
+use rs6502::memory::{Bus, DefaultMemory, Memory};
+
+// one bus cycle, the address, the data and if it was a read
+pub type Access = (u16, u8, bool);
+
+pub const R: bool = true;
+pub const W: bool = false;
+
+// the cycle core and the fast core, to run the same program through both
+pub const CORES: [fn(&mut State, &mut DefaultMemory) -> u32; 2] =
+    [m6502::run_instruction, fast::run_instruction];
+
+// the program at $0600 and the reset vector pointing at it
+fn image(program: &[u8]) -> impl Iterator<Item = (u16, u8)> + '_ {
+    let code = program.iter().enumerate();
+    code.map(|(i, byte)| (0x0600 + i as u16, *byte))
+        .chain([(0xFFFD, 0x06)])
+}
+
+pub fn load(program: &[u8]) -> DefaultMemory {
+    let mut memory = DefaultMemory::new();
+    for (addr, value) in image(program) {
+        memory.set(addr, value);
+    }
+    memory
+}
+
+// a processor that is about to reset into the program
+pub fn machine(program: &[u8]) -> (State, DefaultMemory) {
+    (State::new(), load(program))
+}
+
+// flat memory that records every bus cycle
+pub struct Recorder {
+    pub ram: Vec<u8>,
+    pub accesses: Vec<Access>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder {
+            ram: vec![0; 0x10000],
+            accesses: Vec::new(),
+        }
+    }
+
+    pub fn load(program: &[u8]) -> Recorder {
+        let mut bus = Recorder::new();
+        for (addr, value) in image(program) {
+            bus.ram[addr as usize] = value;
+        }
+        bus
+    }
+}
+
+impl Bus for Recorder {
+    fn read(&mut self, addr: u16) -> u8 {
+        let value = self.ram[addr as usize];
+        self.accesses.push((addr, value, R));
+        value
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.ram[addr as usize] = value;
+        self.accesses.push((addr, value, W));
+    }
+}
@@ -0,0 +1,136 @@
+// ADC and SBC in decimal mode, including the operands that aren't valid BCD.
+//
+// The expected values follow Bruce Clark's "Decimal Mode" tutorial on 6502.org. The NMOS
+// parts take N, V and Z from intermediate results, the 65C02 sets N and Z from the result.
+// Every pair of operands is checked by the decimal test program in `tests/dormann.rs`.
+
+mod common;
+
+use common::machine;
+use rs6502::instruct::Variant;
+use rs6502::m6502;
+
+const N: u8 = 0x80;
+const V: u8 = 0x40;
+const D: u8 = 0x08;
+const Z: u8 = 0x02;
+const C: u8 = 0x01;
+
+// runs `op #operand` with the accumulator and carry given, in decimal mode
+fn run(variant: Variant, op: u8, ac: u8, operand: u8, carry: bool) -> (u8, String) {
+    let (mut state, mut memory) = machine(&[op, operand]);
+    state.variant = variant;
+    m6502::run_instruction(&mut state, &mut memory);
+    state.registers.ac = ac;
+    state.registers.sr = 0x20 | D | carry as u8;
+    m6502::run_instruction(&mut state, &mut memory);
+    let sr = state.registers.sr;
+    let flags = [(N, 'N'), (V, 'V'), (Z, 'Z'), (C, 'C')]
+        .iter()
+        .map(|(flag, name)| if sr & flag != 0 { *name } else { '-' })
+        .collect();
+    (state.registers.ac, flags)
+}
+
+fn adc(variant: Variant, ac: u8, operand: u8, carry: bool) -> (u8, String) {
+    run(variant, 0x69, ac, operand, carry)
+}
+
+fn sbc(variant: Variant, ac: u8, operand: u8, carry: bool) -> (u8, String) {
+    run(variant, 0xE9, ac, operand, carry)
+}
+
+#[test]
+fn nmos_adc() {
+    let vectors = [
+        (0x09, 0x01, false, 0x10, "----"),
+        // invalid digits are still adjusted by 6
+        (0x0F, 0x01, false, 0x16, "----"),
+        // N comes from the result before the high digit is adjusted, Z from the binary sum
+        (0x99, 0x01, false, 0x00, "N--C"),
+        (0x58, 0x46, true, 0x05, "NV-C"),
+        (0x79, 0x00, true, 0x80, "NV--"),
+        (0x50, 0x50, false, 0x00, "NV-C"),
+        (0x80, 0x80, false, 0x60, "-VZC"),
+        (0x9A, 0x00, false, 0x00, "N--C"),
+        (0x00, 0x9A, true, 0x01, "N--C"),
+        (0x9A, 0x9A, false, 0x9A, "-V-C"),
+        (0x2F, 0x4F, false, 0x74, "----"),
+        (0xFF, 0xFF, true, 0x55, "N--C"),
+    ];
+    for (ac, operand, carry, result, flags) in vectors {
+        assert_eq!(
+            adc(Variant::Nmos6502, ac, operand, carry),
+            (result, flags.to_string()),
+            "{ac:02x} + {operand:02x} + {}",
+            carry as u8
+        );
+    }
+}
+
+#[test]
+fn nmos_sbc() {
+    // the flags are the ones of the binary subtraction
+    let vectors = [
+        (0x00, 0x01, true, 0x99, "N---"),
+        (0x46, 0x12, true, 0x34, "---C"),
+        (0x40, 0x13, true, 0x27, "---C"),
+        (0x32, 0x02, false, 0x29, "---C"),
+        (0x12, 0x21, true, 0x91, "N---"),
+        (0x80, 0x01, true, 0x79, "-V-C"),
+        (0x0F, 0x01, true, 0x0E, "---C"),
+        (0x9A, 0x00, true, 0x9A, "N--C"),
+        (0x00, 0x9A, true, 0x00, "----"),
+        (0x9A, 0x0F, false, 0x84, "N--C"),
+        (0x20, 0x0A, true, 0x10, "---C"),
+    ];
+    for (ac, operand, carry, result, flags) in vectors {
+        assert_eq!(
+            sbc(Variant::Nmos6502, ac, operand, carry),
+            (result, flags.to_string()),
+            "{ac:02x} - {operand:02x} - {}",
+            !carry as u8
+        );
+    }
+}
+
+#[test]
+fn cmos_decimal_flags() {
+    // the same sums with N and Z taken from the result
+    let vectors = [
+        (0x99, 0x01, false, 0x00, "--ZC"),
+        (0x58, 0x46, true, 0x05, "-V-C"),
+        (0x80, 0x80, false, 0x60, "-V-C"),
+        (0x9A, 0x9A, false, 0x9A, "NV-C"),
+        (0x0F, 0x01, false, 0x16, "----"),
+    ];
+    for (ac, operand, carry, result, flags) in vectors {
+        assert_eq!(
+            adc(Variant::Cmos65C02, ac, operand, carry),
+            (result, flags.to_string())
+        );
+    }
+    let vectors = [
+        (0x00, 0x01, true, 0x99, "N---"),
+        (0x00, 0x9A, true, 0x00, "--Z-"),
+        (0x9A, 0x0F, false, 0x84, "N--C"),
+    ];
+    for (ac, operand, carry, result, flags) in vectors {
+        assert_eq!(
+            sbc(Variant::Cmos65C02, ac, operand, carry),
+            (result, flags.to_string())
+        );
+    }
+}
+
+#[test]
+fn ricoh_ignores_the_decimal_flag() {
+    assert_eq!(
+        adc(Variant::Ricoh2A03, 0x09, 0x01, false),
+        (0x0A, "----".to_string())
+    );
+    assert_eq!(
+        sbc(Variant::Ricoh2A03, 0x10, 0x01, true),
+        (0x0F, "---C".to_string())
+    );
+}
@@ -0,0 +1,102 @@
+// Klaus Dormann's 6502 test suite (https://github.com/Klaus2m5/6502_65C02_functional_tests).
+//
+// The functional test binary isn't part of the repository, put it in `tests/roms` as the
+// readme there describes and run `cargo test --test dormann -- --ignored`. The decimal test
+// is assembled from `tests/roms/decimal_test.asm` and always runs. Both tests end in a
+// `jmp *` style trap, the trap address tells if they passed.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::CORES;
+use rs6502::asm::assemble_file;
+use rs6502::instruct::Variant;
+use rs6502::m6502::{self, State};
+use rs6502::memory::{DefaultMemory, Memory};
+
+// the success trap of 6502_functional_test.a65 with the default configuration
+const FUNCTIONAL_SUCCESS: u16 = 0x3469;
+// decimal_test.asm stores its result here, 0 means passed, and reads the processor from here
+const DECIMAL_ERROR: u16 = 0x0010;
+const DECIMAL_CMOS: u16 = 0x0011;
+
+fn rom_path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "roms", name]
+        .iter()
+        .collect()
+}
+
+fn rom(name: &str) -> Vec<u8> {
+    let path = rom_path(name);
+    std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "cannot read {}: {e}, see tests/roms/README.md",
+            path.display()
+        )
+    })
+}
+
+// runs from `start` after the reset sequence until the program traps, returns the trap address
+fn run_to_trap(
+    state: &mut State,
+    memory: &mut DefaultMemory,
+    start: u16,
+    run_instruction: fn(&mut State, &mut DefaultMemory) -> u32,
+) -> u16 {
+    run_instruction(state, memory);
+    state.registers.pc = start;
+    // the functional test needs about 30 million instructions
+    for _ in 0..100_000_000 {
+        let pc = state.registers.pc;
+        run_instruction(state, memory);
+        if state.registers.pc == pc {
+            return pc;
+        }
+    }
+    panic!("no trap reached, pc {:04x}", state.registers.pc);
+}
+
+#[test]
+#[ignore = "needs 6502_functional_test.bin in tests/roms"]
+fn functional_test() {
+    let image = rom("6502_functional_test.bin");
+    let mut memory = DefaultMemory::new();
+    for (i, byte) in image.iter().enumerate() {
+        memory.set(i as u16, *byte);
+    }
+    let trap = run_to_trap(
+        &mut State::new(),
+        &mut memory,
+        0x0400,
+        m6502::run_instruction,
+    );
+    assert_eq!(trap, FUNCTIONAL_SUCCESS, "trapped at {trap:04x}");
+}
+
+#[test]
+fn decimal_test() {
+    let program = assemble_file(&rom_path("decimal_test.asm"), Variant::Nmos6502, &[])
+        .unwrap_or_else(|report| panic!("{report}"));
+    for variant in [Variant::Nmos6502, Variant::Cmos65C02] {
+        for run_instruction in CORES {
+            let mut memory = DefaultMemory::new();
+            for (addr, value) in &program {
+                memory.set(*addr, *value);
+            }
+            memory.set(DECIMAL_CMOS, variant.is_cmos() as u8);
+            let mut state = State::new();
+            state.variant = variant;
+            let trap = run_to_trap(&mut state, &mut memory, 0x0200, run_instruction);
+            // the operands that failed are left in N1, N2 and Y
+            assert_eq!(
+                memory.get(DECIMAL_ERROR),
+                0,
+                "{variant}: failed on {:02x} and {:02x} with carry {}, trapped at {trap:04x}",
+                memory.get(0x0000),
+                memory.get(0x0001),
+                state.registers.yr
+            );
+        }
+    }
+}
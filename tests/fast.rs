@@ -0,0 +1,191 @@
+mod common;
+
+use common::CORES;
+use rs6502::asm::{assemble, read_lines};
+use rs6502::fast;
+use rs6502::instruct::Variant;
+use rs6502::m6502::{self, State};
+use rs6502::memory::{DefaultMemory, Memory};
+
+fn load(file: &str) -> DefaultMemory {
+    let lines: Vec<String> = read_lines(format!("{}/{file}", env!("CARGO_MANIFEST_DIR")))
+        .unwrap()
+        .map(|l| l.unwrap())
+        .collect();
+    let mut memory = DefaultMemory::new();
+    for (key, val) in assemble(lines) {
+        memory.set(key, val);
+    }
+    memory.set(0xFFFC, 0x00);
+    memory.set(0xFFFD, 0x06);
+    // the game ends in a BRK, trap it in a `jmp *` loop
+    for (i, byte) in [0x4C, 0xF0, 0xFF].iter().enumerate() {
+        memory.set(0xFFF0 + i as u16, *byte);
+    }
+    memory.set(0xFFFE, 0xF0);
+    memory.set(0xFFFF, 0xFF);
+    memory
+}
+
+#[test]
+fn fast_mode_matches_cycle_level() {
+    let mut slow_memory = load("example.asm");
+    let mut fast_memory = load("example.asm");
+    let mut slow = State::new();
+    let mut fast = State::new();
+
+    for i in 0..20000 {
+        // keep the snake moving and feed the random number generator
+        let random = (i * 37 % 251) as u8;
+        slow_memory.set(0xFE, random);
+        fast_memory.set(0xFE, random);
+
+        let slow_cycles = m6502::run_instruction(&mut slow, &mut slow_memory);
+        let fast_cycles = fast::run_instruction(&mut fast, &mut fast_memory);
+        assert_eq!(slow_cycles, fast_cycles, "cycle count of instruction {i}");
+        assert_eq!(
+            slow.registers, fast.registers,
+            "registers after instruction {i}"
+        );
+        assert_eq!(slow.total_cycles, fast.total_cycles);
+    }
+    for addr in 0..=0xFFFF {
+        assert_eq!(
+            slow_memory.get(addr),
+            fast_memory.get(addr),
+            "memory at {addr:04x}"
+        );
+    }
+}
+
+#[test]
+fn fast_mode_matches_cycle_level_with_interrupts() {
+    // counts in a loop while an IRQ and a NMI handler count their calls
+    let program: [u8; 19] = [
+        0x58, // cli
+        0xE6, 0x00, // loop: inc $00
+        0x4C, 0x01, 0x06, // jmp loop
+        0xE6, 0x01, // irq: inc $01
+        0xA5, 0x00, // lda $00
+        0x85, 0x03, // sta $03
+        0x40, // rti
+        0xE6, 0x02, // nmi: inc $02
+        0x4C, 0x11, 0x06, // jmp ret
+        0x40, // ret: rti
+    ];
+    let mut slow_memory = DefaultMemory::new();
+    let mut fast_memory = DefaultMemory::new();
+    for memory in [&mut slow_memory, &mut fast_memory] {
+        for (i, byte) in program.iter().enumerate() {
+            memory.set(0x0600 + i as u16, *byte);
+        }
+        for (addr, value) in [(0xFFFA, 0x0D), (0xFFFC, 0x00), (0xFFFE, 0x06)] {
+            memory.set(addr, value);
+            memory.set(addr + 1, 0x06);
+        }
+    }
+    let mut slow = State::new();
+    let mut fast = State::new();
+
+    for i in 0..5000 {
+        for state in [&mut slow, &mut fast] {
+            state.irq = i % 50 < 3;
+            state.nmi = i % 170 < 20;
+        }
+        let slow_cycles = m6502::run_instruction(&mut slow, &mut slow_memory);
+        let fast_cycles = fast::run_instruction(&mut fast, &mut fast_memory);
+        assert_eq!(slow_cycles, fast_cycles, "cycle count of instruction {i}");
+        assert_eq!(
+            slow.registers, fast.registers,
+            "registers after instruction {i}"
+        );
+    }
+    assert!(slow_memory.get(0x01) > 0 && slow_memory.get(0x02) > 0);
+    for addr in 0..=0xFFFF {
+        assert_eq!(
+            slow_memory.get(addr),
+            fast_memory.get(addr),
+            "memory at {addr:04x}"
+        );
+    }
+}
+
+#[test]
+fn both_modes_jam_on_illegal_opcodes() {
+    // lda #$01, jam
+    let mut memory = common::load(&[0xA9, 0x01, 0x02]);
+    for run_instruction in CORES {
+        let mut state = State::new();
+        run_instruction(&mut state, &mut memory);
+        run_instruction(&mut state, &mut memory);
+        assert_eq!(run_instruction(&mut state, &mut memory), 1);
+        let fault = state.fault.expect("jammed");
+        assert_eq!((fault.pc, fault.op_code), (0x0602, 0x02));
+
+        // stays jammed until reset
+        let total_cycles = state.total_cycles;
+        assert_eq!(run_instruction(&mut state, &mut memory), 0);
+        assert_eq!(state.total_cycles, total_cycles);
+        state.res = true;
+        run_instruction(&mut state, &mut memory);
+        state.res = false;
+        run_instruction(&mut state, &mut memory);
+        assert_eq!(state.fault, None);
+        assert_eq!(state.registers.pc, 0x0600);
+    }
+}
+
+// fills the memory with noise, which runs every opcode including the undocumented ones
+fn run_random_code(variant: Variant) {
+    let mut slow_memory = DefaultMemory::new();
+    let mut seed: u32 = 1;
+    for addr in 0..=0xFFFF {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        slow_memory.set(addr, (seed >> 16) as u8);
+    }
+    let mut fast_memory = slow_memory.clone();
+    let mut slow = State::new();
+    let mut fast = State::new();
+    for state in [&mut slow, &mut fast] {
+        state.variant = variant;
+        state.undocumented = true;
+    }
+
+    for i in 0..50000 {
+        let slow_cycles = m6502::run_instruction(&mut slow, &mut slow_memory);
+        let fast_cycles = fast::run_instruction(&mut fast, &mut fast_memory);
+        assert_eq!(slow_cycles, fast_cycles, "cycle count of instruction {i}");
+        assert_eq!(
+            slow.registers, fast.registers,
+            "registers after instruction {i}"
+        );
+        assert_eq!(slow.fault, fast.fault, "fault after instruction {i}");
+        assert_eq!(slow.waiting, fast.waiting, "waiting after instruction {i}");
+        if slow.fault.is_some() || slow.waiting {
+            // reset out of the JAM, STP or WAI
+            for state in [&mut slow, &mut fast] {
+                state.res = true;
+            }
+            m6502::run_instruction(&mut slow, &mut slow_memory);
+            fast::run_instruction(&mut fast, &mut fast_memory);
+            for state in [&mut slow, &mut fast] {
+                state.res = false;
+            }
+        }
+    }
+    assert_eq!(slow.port, fast.port);
+    for addr in 0..=0xFFFF {
+        assert_eq!(
+            slow_memory.get(addr),
+            fast_memory.get(addr),
+            "memory at {addr:04x}"
+        );
+    }
+}
+
+#[test]
+fn fast_mode_matches_cycle_level_on_random_code() {
+    for variant in Variant::ALL {
+        run_random_code(variant);
+    }
+}
@@ -0,0 +1,140 @@
+// Single step tests in the format of Tom Harte's ProcessorTests
+// (https://github.com/SingleStepTests/ProcessorTests/tree/main/6502).
+//
+// Every json file in `tests/harte` holds the tests of one opcode, each test has the
+// registers and memory before and after the instruction and every bus cycle in between.
+// Files from the upstream `v1` directory go in `tests/harte/v1`, see the readme there.
+// `tests/harte/extra` has hand written tests in the same format covering every addressing
+// mode and the stack, branch and interrupt timings, they always run. The JAM opcodes are
+// skipped, they lock up the processor instead of finishing an instruction.
+// Set `HARTE_DIR` to run a full checkout of the `v1` directory instead of `tests/harte/v1`.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::Recorder;
+use rs6502::instruct::Instruct;
+use rs6502::m6502::{self, State};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Test {
+    name: String,
+    initial: Snapshot,
+    #[serde(rename = "final")]
+    expected: Snapshot,
+    cycles: Vec<(u16, u8, String)>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+impl Snapshot {
+    fn ram_at(&self, addr: u16) -> u8 {
+        self.ram
+            .iter()
+            .find(|(ram_addr, _)| *ram_addr == addr)
+            .map_or(0, |(_, value)| *value)
+    }
+}
+
+// B and the unused bit don't exist in the status register
+const P_MASK: u8 = 0xCF;
+
+fn run(test: &Test) {
+    if Instruct::from_op_code(test.initial.ram_at(test.initial.pc)).is_none() {
+        return;
+    }
+    let mut bus = Recorder::new();
+    for (addr, value) in &test.initial.ram {
+        bus.ram[*addr as usize] = *value;
+    }
+
+    let mut state = State::new();
+    state.resetting = false;
+    state.undocumented = true;
+    state.registers.pc = test.initial.pc;
+    state.registers.sp = test.initial.s;
+    state.registers.ac = test.initial.a;
+    state.registers.xr = test.initial.x;
+    state.registers.yr = test.initial.y;
+    state.registers.status_pull(test.initial.p);
+
+    // the bus is checked cycle by cycle, so a wrong cycle count shows up as a wrong access
+    for (i, expected) in test.cycles.iter().enumerate() {
+        m6502::cycle(&mut state, &mut bus);
+        let access = bus.accesses.get(i).map(|&(addr, value, read)| {
+            (addr, value, if read { "read" } else { "write" }.to_string())
+        });
+        assert_eq!(access.as_ref(), Some(expected), "{}: cycle {i}", test.name);
+        assert_eq!(state.sync, i == 0, "{}: sync on cycle {i}", test.name);
+    }
+    assert!(
+        state.next_timing.is_fetch(),
+        "{}: instruction not done",
+        test.name
+    );
+
+    let expected = &test.expected;
+    let registers = &state.registers;
+    assert_eq!(registers.pc, expected.pc, "{}: pc", test.name);
+    assert_eq!(registers.sp, expected.s, "{}: s", test.name);
+    assert_eq!(registers.ac, expected.a, "{}: a", test.name);
+    assert_eq!(registers.xr, expected.x, "{}: x", test.name);
+    assert_eq!(registers.yr, expected.y, "{}: y", test.name);
+    assert_eq!(
+        registers.sr & P_MASK,
+        expected.p & P_MASK,
+        "{}: p {}",
+        test.name,
+        registers.fmt_status()
+    );
+    for (addr, value) in &expected.ram {
+        assert_eq!(
+            bus.ram[*addr as usize], *value,
+            "{}: ram {addr:04x}",
+            test.name
+        );
+    }
+}
+
+fn json_files(dir: &PathBuf) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn single_step_tests() {
+    let harte: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "harte"]
+        .iter()
+        .collect();
+    let upstream = match std::env::var("HARTE_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => harte.join("v1"),
+    };
+    let extra_dir = harte.join("extra");
+    let extra = json_files(&extra_dir);
+    assert!(!extra.is_empty(), "no tests in {}", extra_dir.display());
+
+    for file in json_files(&upstream).into_iter().chain(extra) {
+        let tests: Vec<Test> = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        for test in &tests {
+            run(test);
+        }
+    }
+}
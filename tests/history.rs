@@ -0,0 +1,95 @@
+mod common;
+
+use rs6502::history::History;
+use rs6502::m6502::State;
+use rs6502::memory::{DefaultMemory, Memory};
+
+fn machine() -> (State, DefaultMemory) {
+    // fills a page through the stack and a pointer
+    let program = [
+        0xA2, 0x00, // ldx #$00
+        0xA9, 0x00, // lda #$00
+        0x85, 0x10, // sta $10
+        0xA9, 0x03, // lda #$03
+        0x85, 0x11, // sta $11
+        0x8A, // loop: txa
+        0x48, // pha
+        0xA0, 0x00, // ldy #$00
+        0x91, 0x10, // sta ($10),y
+        0xE6, 0x10, // inc $10
+        0x68, // pla
+        0xE8, // inx
+        0x4C, 0x0A, 0x06, // jmp loop
+    ];
+    common::machine(&program)
+}
+
+// the zero page, the stack and the page the program fills
+fn dump(memory: &DefaultMemory) -> Vec<u8> {
+    (0..0x0400).map(|addr| memory.get(addr)).collect()
+}
+
+#[test]
+fn half_steps_are_undone_one_by_one() {
+    let (mut state, mut memory) = machine();
+    let mut history = History::new(10_000);
+    let mut seen = Vec::new();
+    for _ in 0..2000 {
+        seen.push((format!("{state:?}"), dump(&memory)));
+        history.half_step(&mut state, &mut memory);
+    }
+    assert_ne!(memory.get(0x0301), 0);
+
+    while let Some((expected_state, expected_memory)) = seen.pop() {
+        assert!(history.half_step_back(&mut state, &mut memory));
+        assert_eq!(format!("{state:?}"), expected_state);
+        assert_eq!(
+            dump(&memory),
+            expected_memory,
+            "after {} half steps",
+            seen.len()
+        );
+    }
+    assert!(!history.half_step_back(&mut state, &mut memory));
+    assert!(history.is_empty());
+}
+
+#[test]
+fn cycles_and_instructions_are_undone() {
+    let (mut state, mut memory) = machine();
+    let mut history = History::new(10_000);
+    let mut instructions = Vec::new();
+    for _ in 0..100 {
+        instructions.push((format!("{state:?}"), dump(&memory)));
+        history.run_instruction(&mut state, &mut memory);
+    }
+
+    // part of the next instruction, then back to its start
+    history.cycle(&mut state, &mut memory);
+    history.cycle(&mut state, &mut memory);
+    history.half_step(&mut state, &mut memory);
+    assert_eq!(history.cycle_back(&mut state, &mut memory), 1);
+    assert!(state.clock1);
+    assert_eq!(history.instruction_back(&mut state, &mut memory), 4);
+    assert!(state.next_timing.is_fetch());
+
+    while let Some((expected_state, expected_memory)) = instructions.pop() {
+        assert!(history.instruction_back(&mut state, &mut memory) > 0);
+        assert_eq!(format!("{state:?}"), expected_state);
+        assert_eq!(dump(&memory), expected_memory);
+    }
+    assert_eq!(history.instruction_back(&mut state, &mut memory), 0);
+}
+
+#[test]
+fn history_is_bounded() {
+    let (mut state, mut memory) = machine();
+    let mut history = History::new(100);
+    for _ in 0..1000 {
+        history.cycle(&mut state, &mut memory);
+    }
+    assert_eq!(history.len(), 100);
+    let total_cycles = state.total_cycles;
+    while history.half_step_back(&mut state, &mut memory) {}
+    assert_eq!(state.total_cycles, total_cycles - 50);
+}
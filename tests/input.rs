@@ -0,0 +1,181 @@
+use rs6502::asm::{assemble, read_lines};
+use rs6502::input::{Input, Recording, Replay};
+use rs6502::instruct::Variant;
+use rs6502::m6502::{self, State};
+use rs6502::memory::{DefaultMemory, Memory};
+
+// the snake game reads a random number from $FE and the last key from $FF
+fn snake() -> DefaultMemory {
+    let lines: Vec<String> = read_lines(format!("{}/example.asm", env!("CARGO_MANIFEST_DIR")))
+        .unwrap()
+        .map(|l| l.unwrap())
+        .collect();
+    let mut memory = DefaultMemory::new();
+    for (key, val) in assemble(lines) {
+        memory.set(key, val);
+    }
+    memory.set(0xFFFC, 0x00);
+    memory.set(0xFFFD, 0x06);
+    memory
+}
+
+#[test]
+fn replay_is_identical() {
+    let mut memory = snake();
+    let mut state = State::new();
+    let mut recording = Recording::new();
+    let mut seed: u32 = 7;
+    for i in 0..100_000 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        if i % 3 == 0 {
+            let random = (seed >> 16) as u8;
+            recording.input(&mut state, &mut memory, Input::Write(0xFE, random));
+        }
+        if i % 5000 == 0 {
+            let key = [0x77, 0x64, 0x73, 0x61][(seed >> 24) as usize % 4];
+            recording.input(&mut state, &mut memory, Input::Write(0xFF, key));
+        }
+        if i % 777 == 0 {
+            recording.input(&mut state, &mut memory, Input::Nmi(i % 2 == 0));
+        }
+        if i == 50_000 {
+            recording.input(&mut state, &mut memory, Input::Res(true));
+        }
+        if i == 50_010 {
+            recording.input(&mut state, &mut memory, Input::Res(false));
+        }
+        // inputs land on both halves of a cycle
+        m6502::half_step(&mut state, &mut memory);
+    }
+
+    let recording = Recording::parse(&recording.to_string()).unwrap();
+    let mut replayed = State::new();
+    let mut replayed_memory = snake();
+    let mut replay = Replay::new(&recording);
+    while !replay.is_done() || replayed.half_cycles != state.half_cycles {
+        replay.half_step(&mut replayed, &mut replayed_memory);
+    }
+    assert_eq!(format!("{replayed:?}"), format!("{state:?}"));
+    for addr in 0..=0xFFFF {
+        assert_eq!(
+            replayed_memory.get(addr),
+            memory.get(addr),
+            "memory at {addr:04x}"
+        );
+    }
+}
+
+// an NMI pulse that comes and goes while WAI stops the processor still wakes it up
+#[test]
+fn inputs_while_waiting_replay() {
+    let machine = || {
+        let mut memory = DefaultMemory::new();
+        // wai, then loop at $0601, the NMI handler loops at $0610
+        for (addr, value) in [
+            (0x0600, 0xCB),
+            (0x0601, 0x4C),
+            (0x0602, 0x01),
+            (0x0603, 0x06),
+        ] {
+            memory.set(addr, value);
+        }
+        for (addr, value) in [(0x0610, 0x4C), (0x0611, 0x10), (0x0612, 0x06)] {
+            memory.set(addr, value);
+        }
+        memory.set(0xFFFB, 0x06);
+        memory.set(0xFFFA, 0x10);
+        memory.set(0xFFFD, 0x06);
+        let mut state = State::new();
+        state.variant = Variant::Cmos65C02;
+        (state, memory)
+    };
+    let (mut state, mut memory) = machine();
+    let mut recording = Recording::new();
+    while !state.waiting {
+        m6502::half_step(&mut state, &mut memory);
+    }
+    recording.input(&mut state, &mut memory, Input::Nmi(true));
+    m6502::half_step(&mut state, &mut memory);
+    m6502::half_step(&mut state, &mut memory);
+    recording.input(&mut state, &mut memory, Input::Nmi(false));
+    for _ in 0..40 {
+        m6502::half_step(&mut state, &mut memory);
+    }
+    assert_eq!(state.registers.pc & 0xFFF0, 0x0610);
+
+    let (mut replayed, mut replayed_memory) = machine();
+    let mut replay = Replay::new(&recording);
+    while replayed.half_cycles != state.half_cycles {
+        replay.half_step(&mut replayed, &mut replayed_memory);
+    }
+    assert_eq!(format!("{replayed:?}"), format!("{state:?}"));
+}
+
+// RES pressed and released before the clock moved on never reaches the processor
+#[test]
+fn reset_without_a_half_cycle_replays() {
+    let mut memory = snake();
+    let mut state = State::new();
+    let mut recording = Recording::new();
+    for _ in 0..100 {
+        m6502::half_step(&mut state, &mut memory);
+    }
+    recording.input(&mut state, &mut memory, Input::Res(true));
+    recording.input(&mut state, &mut memory, Input::Res(false));
+    for _ in 0..100 {
+        m6502::half_step(&mut state, &mut memory);
+    }
+
+    let mut replayed = State::new();
+    let mut replayed_memory = snake();
+    let mut replay = Replay::new(&recording);
+    while replayed.half_cycles != state.half_cycles {
+        replay.half_step(&mut replayed, &mut replayed_memory);
+    }
+    assert_eq!(format!("{replayed:?}"), format!("{state:?}"));
+}
+
+// a replay made again for every half step picks up at the point the machine is at
+#[test]
+fn replays_go_on_from_the_current_point() {
+    let mut memory = snake();
+    let mut state = State::new();
+    let mut recording = Recording::new();
+    for i in 0..2000 {
+        if i % 3 == 0 {
+            recording.input(&mut state, &mut memory, Input::Write(0xFE, i as u8));
+        }
+        if i % 500 == 0 {
+            recording.input(&mut state, &mut memory, Input::Nmi(i % 1000 == 0));
+        }
+        m6502::half_step(&mut state, &mut memory);
+    }
+
+    let mut replayed = State::new();
+    let mut replayed_memory = snake();
+    while replayed.half_cycles != state.half_cycles {
+        let mut replay = Replay::new(&recording);
+        replay.seek(&replayed);
+        replay.half_step(&mut replayed, &mut replayed_memory);
+    }
+    assert_eq!(format!("{replayed:?}"), format!("{state:?}"));
+    assert_eq!(replayed_memory.get(0xFE), memory.get(0xFE));
+}
+
+#[test]
+fn recordings_are_text() {
+    let mut state = State::new();
+    let mut memory = DefaultMemory::new();
+    let mut recording = Recording::new();
+    recording.input(&mut state, &mut memory, Input::Irq(true));
+    m6502::half_step(&mut state, &mut memory);
+    recording.input(&mut state, &mut memory, Input::Write(0xFF, 0x77));
+    assert_eq!(
+        recording.to_string(),
+        "# rs6502 input recording 2\n0 φ1 irq 1\n1 φ2 write 00ff 77\n"
+    );
+
+    assert!(Recording::parse("0 φ1 irq 1\n").is_err());
+    let error = Recording::parse("# rs6502 input recording 2\n0 φ3 irq 1\n").unwrap_err();
+    assert!(error.to_string().starts_with("line 2"), "{error}");
+}
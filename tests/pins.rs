@@ -0,0 +1,159 @@
+mod common;
+
+use common::{load, CORES};
+use rs6502::fast;
+use rs6502::instruct::Variant;
+use rs6502::m6502::{self, State};
+use rs6502::memory::Memory;
+
+#[test]
+fn rdy_stalls_read_cycles() {
+    let mut memory = load(&[
+        0xA9, 0x42, // lda #$42
+        0x85, 0x10, // sta $10
+        0xA5, 0x10, // lda $10
+        0x4C, 0x06, 0x06, // jmp *
+    ]);
+    let mut state = State::new();
+    m6502::run_instruction(&mut state, &mut memory);
+    m6502::run_instruction(&mut state, &mut memory);
+
+    // stall the operand fetch of sta
+    m6502::cycle(&mut state, &mut memory);
+    state.rdy = false;
+    m6502::cycle(&mut state, &mut memory);
+    assert!(state.stalled);
+    let ab = state.ab;
+    for _ in 0..5 {
+        m6502::cycle(&mut state, &mut memory);
+        assert!(state.rw && state.stalled);
+        assert_eq!(state.ab, ab);
+    }
+    state.rdy = true;
+    m6502::run_instruction(&mut state, &mut memory);
+    assert_eq!(memory.get(0x10), 0x42);
+    // 7 reset + 2 lda + 3 sta + 6 stalled cycles
+    assert_eq!(state.total_cycles, 18);
+
+    m6502::run_instruction(&mut state, &mut memory);
+    assert_eq!(state.registers.ac, 0x42);
+}
+
+#[test]
+fn rdy_is_ignored_on_write_cycles() {
+    let mut memory = load(&[
+        0xA9, 0x42, // lda #$42
+        0x85, 0x10, // sta $10
+        0x4C, 0x04, 0x06, // jmp *
+    ]);
+    let mut state = State::new();
+    m6502::run_instruction(&mut state, &mut memory);
+    m6502::run_instruction(&mut state, &mut memory);
+    m6502::cycle(&mut state, &mut memory);
+    m6502::cycle(&mut state, &mut memory);
+
+    // the write runs through, the processor stops on the next opcode fetch
+    state.rdy = false;
+    m6502::cycle(&mut state, &mut memory);
+    assert!(!state.rw && !state.stalled);
+    assert_eq!(memory.get(0x10), 0x42);
+    m6502::cycle(&mut state, &mut memory);
+    assert!(state.stalled);
+    assert_eq!(state.ab, 0x0604);
+
+    // the 65C02 stalls on writes too
+    let mut state = State::new();
+    state.variant = Variant::Cmos65C02;
+    memory.set(0x10, 0);
+    m6502::run_instruction(&mut state, &mut memory);
+    m6502::run_instruction(&mut state, &mut memory);
+    m6502::cycle(&mut state, &mut memory);
+    m6502::cycle(&mut state, &mut memory);
+    state.rdy = false;
+    m6502::cycle(&mut state, &mut memory);
+    assert!(!state.rw && state.stalled);
+}
+
+#[test]
+fn rdy_stalls_both_modes() {
+    let mut memory = load(&[
+        0xE6, 0x10, // loop: inc $10
+        0x4C, 0x00, 0x06, // jmp loop
+    ]);
+    let mut slow_memory = memory.clone();
+    let mut slow = State::new();
+    let mut fast = State::new();
+    for i in 0..2000 {
+        for state in [&mut slow, &mut fast] {
+            state.rdy = i % 30 >= 8;
+        }
+        let slow_cycles = m6502::run_instruction(&mut slow, &mut slow_memory);
+        let fast_cycles = fast::run_instruction(&mut fast, &mut memory);
+        assert_eq!(slow_cycles, fast_cycles, "cycle count of step {i}");
+        assert_eq!(slow.registers, fast.registers, "registers after step {i}");
+        assert_eq!(slow.total_cycles, fast.total_cycles);
+    }
+    assert_eq!(slow_memory.get(0x10), memory.get(0x10));
+}
+
+#[test]
+fn so_sets_overflow_on_the_edge() {
+    let program = [
+        0xB8, // clv
+        0x50, 0xFE, // bvc *
+        0xB8, // clv
+        0x50, 0xFE, // bvc *
+        0xE6, 0x10, // inc $10
+        0x4C, 0x08, 0x06, // jmp *
+    ];
+    for run_instruction in CORES {
+        let mut memory = load(&program);
+        let mut state = State::new();
+        for _ in 0..20 {
+            run_instruction(&mut state, &mut memory);
+        }
+        assert_eq!(state.registers.pc, 0x0601);
+
+        state.so = true;
+        for _ in 0..20 {
+            run_instruction(&mut state, &mut memory);
+        }
+        // holding SO doesn't set V again after the clv
+        assert_eq!(state.registers.pc, 0x0604);
+
+        state.so = false;
+        run_instruction(&mut state, &mut memory);
+        state.so = true;
+        for _ in 0..20 {
+            run_instruction(&mut state, &mut memory);
+        }
+        assert_eq!(memory.get(0x10), 1);
+    }
+}
+
+#[test]
+fn sync_marks_the_opcode_fetches() {
+    let mut memory = load(&[
+        0xA9, 0x42, // lda #$42
+        0x85, 0x10, // sta $10
+        0xEA, // nop
+        0x4C, 0x05, 0x06, // jmp *
+    ]);
+    let mut state = State::new();
+    m6502::run_instruction(&mut state, &mut memory);
+
+    let pins = m6502::trace(&mut state, &mut memory, 2 * 10);
+    let fetches: Vec<u16> = pins
+        .iter()
+        .filter(|pins| pins.sync && pins.phi2)
+        .map(|pins| pins.ab)
+        .collect();
+    assert_eq!(fetches, [0x0600, 0x0602, 0x0604, 0x0605]);
+
+    // both halves of a cycle show the same address, the data is valid in φ2
+    assert!(pins[0].phi1 && pins[0].sync && pins[0].rw);
+    assert_eq!((pins[1].ab, pins[1].db), (0x0600, 0xA9));
+    assert!(pins[1].phi2 && pins[1].sync);
+    let write = pins.iter().find(|pins| !pins.rw && pins.phi2).unwrap();
+    assert_eq!((write.ab, write.db, write.sync), (0x0010, 0x42, false));
+}
@@ -0,0 +1,249 @@
+; Decimal mode ADC and SBC, every pair of operands with both values of the carry.
+;
+; Written after the test program in appendix B of Bruce Clark's "Decimal Mode" tutorial on
+; 6502.org, the program Klaus Dormann's 6502_decimal_test.a65 is built on. The expected
+; results are worked out with binary arithmetic, the way the tutorial derives them, and
+; compared with what ADC and SBC do in decimal mode. All of A, N, V, Z and C are checked,
+; the invalid BCD operands too.
+;
+; Set CMOS to 1 before starting for the 65C02 results, N and Z from the accumulator and
+; the 65C02 way of adjusting SBC. The program starts at $0200 and ends in the `done` loop,
+; with ERROR at 0 when every result matched. Otherwise N1, N2 and Y (the carry) are the
+; operands that failed.
+
+define N1     $00 ; first operand
+define N2     $01 ; second operand
+define N1L    $02 ; N1 & $0F
+define N1H    $03 ; N1 & $F0
+define N2L    $04 ; N2 & $0F
+define N2H    $05 ; N2 & $F0, followed by (N2 & $F0) + $0F
+define DA     $07 ; accumulator in decimal mode
+define DNVZC  $08 ; flags in decimal mode
+define HA     $09 ; accumulator in binary mode
+define HNVZC  $0A ; flags in binary mode
+define AR     $0B ; predicted accumulator
+define NF     $0C ; predicted N in bit 7
+define VF     $0D ; predicted V in bit 6
+define ZF     $0E ; predicted Z in bit 1
+define CF     $0F ; predicted C in bit 0
+define ERROR  $10 ; 0 when the test passed
+define CMOS   $11 ; 1 for the 65C02 results
+
+.ORG $0200
+test:
+  cld
+  ldy #1         ; Y goes through both values of the carry
+  sty ERROR
+  lda #0
+  sta N1
+  sta N2
+next1:
+  lda N2
+  and #$0F
+  sta N2L
+  lda N2
+  and #$F0
+  sta N2H
+  ora #$0F
+  sta N2H+1
+next2:
+  lda N1
+  and #$0F
+  sta N1L
+  lda N1
+  and #$F0
+  sta N1H
+  jsr add
+  jsr add_flags
+  jsr compare
+  bne done
+  jsr sub
+  jsr sub_flags
+  jsr compare
+  bne done
+  inc N1
+  bne next2
+  inc N2
+  bne next1
+  dey
+  bpl next1
+  lda #0
+  sta ERROR
+done:
+  jmp done
+
+; the results of N1 + N2 + C in decimal and binary mode, and the predicted accumulator,
+; carry and V
+add:
+  sed
+  cpy #1         ; the carry is set when Y is 1
+  lda N1
+  adc N2
+  sta DA
+  php
+  pla
+  sta DNVZC
+  cld
+  cpy #1
+  lda N1
+  adc N2
+  sta HA
+  php
+  pla
+  sta HNVZC
+  cpy #1
+  lda N1L
+  adc N2L
+  cmp #$0A
+  ldx #0
+  bcc add_low
+  inx
+  adc #5         ; adds 6, the carry is set
+  and #$0F
+  sec
+add_low:
+  ora N1H
+  ; adds N2 & $F0 without a decimal carry from the low digit, (N2 & $F0) + $10 with one
+  adc N2H,x
+  php            ; V of the signed sum
+  bcs add_high
+  cmp #$A0
+  bcc add_done
+add_high:
+  adc #$5F       ; adds $60, the carry is set
+  sec
+add_done:
+  sta AR
+  php
+  pla
+  sta CF
+  pla
+  sta VF
+  rts
+
+; the results of N1 - N2 - !C in decimal and binary mode
+sub:
+  sed
+  cpy #1
+  lda N1
+  sbc N2
+  sta DA
+  php
+  pla
+  sta DNVZC
+  cld
+  cpy #1
+  lda N1
+  sbc N2
+  sta HA
+  php
+  pla
+  sta HNVZC
+  rts
+
+; the predicted SBC accumulator of the NMOS parts
+sub_nmos:
+  cpy #1
+  lda N1L
+  sbc N2L
+  ldx #0
+  bcs sub_nmos_low
+  inx
+  sbc #5         ; subtracts 6, the carry is clear
+  and #$0F
+  clc
+sub_nmos_low:
+  ora N1H
+  ; subtracts N2 & $F0 without a borrow from the low digit, (N2 & $F0) + $10 with one
+  sbc N2H,x
+  bcs sub_nmos_done
+  sbc #$5F       ; subtracts $60, the carry is clear
+sub_nmos_done:
+  sta AR
+  rts
+
+; the predicted SBC accumulator of the 65C02, the low digit is adjusted last
+sub_cmos:
+  cpy #1
+  lda N1L
+  sbc N2L
+  ldx #0
+  bcs sub_cmos_low
+  inx
+  and #$0F
+  clc
+sub_cmos_low:
+  ora N1H
+  sbc N2H,x
+  bcs sub_cmos_high
+  sbc #$5F
+sub_cmos_high:
+  cpx #0
+  beq sub_cmos_done
+  sbc #6         ; the carry is set by the cpx
+sub_cmos_done:
+  sta AR
+  rts
+
+; the NMOS parts take N from the signed sum and Z from the binary sum
+add_flags:
+  lda CMOS
+  bne add_flags_cmos
+  lda VF
+  sta NF
+  lda HNVZC
+  sta ZF
+  rts
+add_flags_cmos:
+  lda AR
+  php
+  pla
+  sta NF
+  sta ZF
+  rts
+
+; SBC has the flags of the binary subtraction, the 65C02 sets N and Z from the result
+sub_flags:
+  lda CMOS
+  bne sub_flags_cmos
+  jsr sub_nmos
+  lda HNVZC
+  sta NF
+  sta VF
+  sta ZF
+  sta CF
+  rts
+sub_flags_cmos:
+  jsr sub_cmos
+  lda AR
+  php
+  pla
+  sta NF
+  sta ZF
+  lda HNVZC
+  sta VF
+  sta CF
+  rts
+
+; Z is clear when the decimal mode results aren't the predicted ones
+compare:
+  lda DA
+  cmp AR
+  bne compare_done
+  lda DNVZC
+  eor NF
+  and #$80
+  bne compare_done
+  lda DNVZC
+  eor VF
+  and #$40
+  bne compare_done
+  lda DNVZC
+  eor ZF
+  and #$02
+  bne compare_done
+  lda DNVZC
+  eor CF
+  and #$01
+compare_done:
+  rts
@@ -0,0 +1,64 @@
+// The exit status of rs6502-run, it is what scripts and CI look at.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// writes the program to `main.asm` in a fresh directory
+fn program(test: &str, lines: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rs6502-run-{test}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.asm");
+    fs::write(&path, lines.join("\n")).unwrap();
+    path
+}
+
+fn run(args: &[&str], path: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rs6502-run"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn until_only_passes_when_reached() {
+    let path = program("until", &[".ORG $0600", "lda #$01", "done: jmp done"]);
+    let args = ["--reset", "0600", "--max-cycles", "100"];
+    assert_eq!(run(&args, &path).status.code(), Some(0));
+
+    let args = ["--reset", "0600", "--until", "0602", "--max-cycles", "100"];
+    assert_eq!(run(&args, &path).status.code(), Some(0));
+    // the trap isn't the address that was asked for
+    let args = ["--reset", "0600", "--until", "0700", "--max-cycles", "100"];
+    assert_eq!(run(&args, &path).status.code(), Some(1));
+
+    let path = program("until-brk", &[".ORG $0600", "lda #$01", "brk"]);
+    let args = ["--reset", "0600", "--until", "0700", "--max-cycles", "100"];
+    assert_eq!(run(&args, &path).status.code(), Some(1));
+}
+
+#[test]
+fn assembler_errors_are_reported() {
+    let path = program("error", &[".ORG $0600", "lda #$01", "bogus $10"]);
+    let output = run(&["--reset", "0600"], &path);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("ERROR: "), "{stderr}");
+    assert!(stderr.contains("main.asm:3:"), "{stderr}");
+}
+
+#[test]
+fn includes_are_looked_up_in_the_given_directories() {
+    let path = program("include", &[".ORG $0600", ".include \"lib.asm\""]);
+    let lib = path.parent().unwrap().join("lib");
+    fs::create_dir_all(&lib).unwrap();
+    fs::write(lib.join("lib.asm"), "lda #$01\ndone: jmp done").unwrap();
+
+    let output = run(&["--reset", "0600", "--until", "0602"], &path);
+    assert_eq!(output.status.code(), Some(2));
+    let lib = lib.to_str().unwrap();
+    let args = ["--reset", "0600", "--until", "0602", "-I", lib];
+    assert_eq!(run(&args, &path).status.code(), Some(0));
+}
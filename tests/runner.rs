@@ -0,0 +1,98 @@
+mod common;
+
+use common::machine;
+use rs6502::loader::{load, Format};
+use rs6502::m6502::Fault;
+use rs6502::memory::{DefaultMemory, Memory};
+use rs6502::runner::{run_until, Limits, Stop};
+
+#[test]
+fn images_are_loaded() {
+    let mut memory = DefaultMemory::new();
+    let range = load(&[0x00, 0x10, 0xA9, 0x01], Format::Prg, None, &mut memory).unwrap();
+    assert_eq!(range, Some((0x1000, 0x1001)));
+    assert_eq!(memory.get(0x1000), 0xA9);
+    load(&[0x00, 0x10, 0xEA], Format::Prg, Some(0x2000), &mut memory).unwrap();
+    assert_eq!(memory.get(0x2000), 0xEA);
+    assert!(load(&[0x00], Format::Prg, None, &mut memory).is_err());
+
+    let hex = ":03060000A9FF60EF\n:020000040000FA\n:00000001FF\n";
+    let range = load(hex.as_bytes(), Format::IntelHex, None, &mut memory).unwrap();
+    assert_eq!(range, Some((0x0600, 0x0602)));
+    assert_eq!(
+        [0x0600, 0x0601, 0x0602].map(|addr| memory.get(addr)),
+        [0xA9, 0xFF, 0x60]
+    );
+    let error = load(b":03060000A9FF60EE\n", Format::IntelHex, None, &mut memory).unwrap_err();
+    assert_eq!(error.to_string(), "line 1: bad checksum");
+    let error = load(
+        b":020000040001F9\n:01000000EA15\n",
+        Format::IntelHex,
+        None,
+        &mut memory,
+    );
+    assert!(error.is_err(), "above the 64 KiB");
+
+    assert!(load(&[0; 2], Format::Raw, Some(0xFFFF), &mut memory).is_err());
+    assert_eq!(Format::from_path("test.PRG"), Format::Prg);
+    assert_eq!(Format::from_path("test.ihex"), Format::IntelHex);
+    assert_eq!(Format::from_path("test.bin"), Format::Raw);
+}
+
+#[test]
+fn runs_to_a_stop() {
+    let program = [
+        0xA2, 0x05, // ldx #$05
+        0xCA, // loop: dex
+        0xD0, 0xFD, // bne loop
+        0x00, // brk
+    ];
+    let (mut state, mut memory) = machine(&program);
+    assert_eq!(
+        run_until(&mut state, &mut memory, &Limits::default()),
+        Stop::Brk(0x0605)
+    );
+    assert_eq!(state.registers.xr, 0);
+
+    let (mut state, mut memory) = machine(&program);
+    let limits = Limits {
+        until: Some(0x0603),
+        ..Limits::default()
+    };
+    assert_eq!(
+        run_until(&mut state, &mut memory, &limits),
+        Stop::Reached(0x0603)
+    );
+    assert_eq!(state.registers.xr, 4);
+
+    let (mut state, mut memory) = machine(&program);
+    let limits = Limits {
+        max_cycles: Some(10),
+        ..Limits::default()
+    };
+    assert_eq!(
+        run_until(&mut state, &mut memory, &limits),
+        Stop::CycleLimit
+    );
+    assert!(state.total_cycles >= 10);
+}
+
+#[test]
+fn traps_and_jams_stop() {
+    // jmp *
+    let (mut state, mut memory) = machine(&[0xEA, 0x4C, 0x01, 0x06]);
+    assert_eq!(
+        run_until(&mut state, &mut memory, &Limits::default()),
+        Stop::Trap(0x0601)
+    );
+
+    let (mut state, mut memory) = machine(&[0xEA, 0x02]);
+    let fault = Fault {
+        pc: 0x0601,
+        op_code: 0x02,
+    };
+    assert_eq!(
+        run_until(&mut state, &mut memory, &Limits::default()),
+        Stop::Jammed(fault)
+    );
+}
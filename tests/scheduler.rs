@@ -0,0 +1,107 @@
+mod common;
+
+use common::load;
+use rs6502::m6502::State;
+use rs6502::memory::{DefaultMemory, Memory};
+use rs6502::scheduler::Scheduler;
+
+// a timer that pulls IRQ low every `PERIOD` cycles for a few cycles
+const PERIOD: u64 = 20000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Timer {
+    Fire,
+    Release,
+}
+
+fn machine() -> (State, DefaultMemory) {
+    let program = [
+        0x58, // cli
+        0xE6, 0x10, // loop: inc $10
+        0x4C, 0x01, 0x06, // jmp loop
+        0xE6, 0x11, // irq: inc $11
+        0x40, // rti
+    ];
+    let mut memory = load(&program);
+    memory.set(0xFFFE, 0x06);
+    memory.set(0xFFFF, 0x06);
+    (State::new(), memory)
+}
+
+fn timer(
+    fired: &mut Vec<(u64, u64)>,
+) -> impl FnMut(Timer, &mut State, &mut DefaultMemory, &mut Scheduler<Timer>) + '_ {
+    |event, state, _, scheduler| match event {
+        Timer::Fire => {
+            fired.push((scheduler.now(), state.total_cycles));
+            state.irq = true;
+            scheduler.schedule(10, Timer::Release);
+            scheduler.schedule(PERIOD, Timer::Fire);
+        }
+        Timer::Release => state.irq = false,
+    }
+}
+
+#[test]
+fn events_fire_on_their_cycle() {
+    let (mut state, mut memory) = machine();
+    let mut scheduler = Scheduler::new();
+    scheduler.schedule(PERIOD, Timer::Fire);
+    let mut fired = Vec::new();
+    scheduler.run(
+        &mut state,
+        &mut memory,
+        10 * PERIOD + 100,
+        timer(&mut fired),
+    );
+
+    let expected: Vec<(u64, u64)> = (1..=10).map(|i| (i * PERIOD, i * PERIOD)).collect();
+    assert_eq!(fired, expected);
+    assert_eq!(memory.get(0x11), 10);
+    assert_eq!(scheduler.now(), 10 * PERIOD + 100);
+}
+
+#[test]
+fn instruction_level_runs_fire_on_the_next_boundary() {
+    let (mut state, mut memory) = machine();
+    let mut scheduler = Scheduler::new();
+    scheduler.schedule(PERIOD, Timer::Fire);
+    let mut fired = Vec::new();
+    scheduler.run_fast(
+        &mut state,
+        &mut memory,
+        10 * PERIOD + 100,
+        timer(&mut fired),
+    );
+
+    assert_eq!(fired.len(), 10);
+    // the timer schedules the next interrupt from when it actually fired
+    let mut due = PERIOD;
+    for (now, _) in fired {
+        assert!(
+            (due..due + 7).contains(&now),
+            "fired at {now}, due at {due}"
+        );
+        due = now + PERIOD;
+    }
+    assert_eq!(memory.get(0x11), 10);
+}
+
+#[test]
+fn the_master_clock_runs_during_reset() {
+    let (mut state, mut memory) = machine();
+    let mut scheduler = Scheduler::new();
+    let release = scheduler.schedule(50, false);
+    scheduler.schedule(10, true);
+    scheduler.run(&mut state, &mut memory, 100, |res, state, _, _| {
+        state.res = res
+    });
+    assert_eq!(scheduler.now(), 100);
+    assert_eq!(state.total_cycles, 60);
+    assert!(scheduler.cancel(release).is_none());
+
+    let id = scheduler.schedule(5, true);
+    assert_eq!(scheduler.next_time(), Some(105));
+    assert_eq!(scheduler.cancel(id), Some(true));
+    assert!(scheduler.is_empty());
+}
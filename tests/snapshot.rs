@@ -0,0 +1,109 @@
+use std::io::Cursor;
+
+use rs6502::fast;
+use rs6502::instruct::{Quirks, Variant};
+use rs6502::m6502::{self, State};
+use rs6502::memory::{DefaultMemory, Memory};
+use rs6502::snapshot::Snapshot;
+
+fn machine() -> (State, DefaultMemory) {
+    let mut memory = DefaultMemory::new();
+    // count in a loop while storing to the 6510 port
+    let program = [
+        0xE6, 0x10, // loop: inc $10
+        0xA5, 0x10, // lda $10
+        0x85, 0x01, // sta $01
+        0x4C, 0x00, 0x06, // jmp loop
+    ];
+    for (i, byte) in program.iter().enumerate() {
+        memory.set(0x0600 + i as u16, *byte);
+    }
+    memory.set(0xFFFD, 0x06);
+    let mut state = State::new();
+    state.variant = Variant::Mos6510;
+    state.quirks = Some(Quirks {
+        indirect_jump_wrap: false,
+        zero_page_wrap: true,
+        pointer_wrap: false,
+    });
+    state.port.direction = 0x0F;
+    (state, memory)
+}
+
+#[test]
+fn snapshots_restore_the_machine() {
+    let (mut state, mut memory) = machine();
+    // stop in the middle of an instruction
+    for _ in 0..1001 {
+        m6502::half_step(&mut state, &mut memory);
+    }
+
+    let mut file = Vec::new();
+    Snapshot::new(&state, &memory).write(&mut file).unwrap();
+    let snapshot = Snapshot::read(&mut Cursor::new(&file)).unwrap();
+    assert_eq!(format!("{:?}", snapshot.state), format!("{state:?}"));
+
+    let (mut restored, mut restored_memory) = (snapshot.state, snapshot.memory);
+    for _ in 0..1000 {
+        m6502::half_step(&mut state, &mut memory);
+        m6502::half_step(&mut restored, &mut restored_memory);
+        assert_eq!(format!("{restored:?}"), format!("{state:?}"));
+    }
+    for _ in 0..100 {
+        fast::run_instruction(&mut state, &mut memory);
+        fast::run_instruction(&mut restored, &mut restored_memory);
+    }
+    assert_eq!(format!("{restored:?}"), format!("{state:?}"));
+    for addr in 0..=0xFFFF {
+        assert_eq!(
+            restored_memory.get(addr),
+            memory.get(addr),
+            "memory at {addr:04x}"
+        );
+    }
+}
+
+#[test]
+fn snapshots_check_the_header() {
+    let (state, memory) = machine();
+    let mut file = Vec::new();
+    Snapshot::new(&state, &memory).write(&mut file).unwrap();
+
+    let mut newer = file.clone();
+    newer[8] += 1;
+    let error = Snapshot::read(&mut Cursor::new(&newer)).unwrap_err();
+    assert!(error.to_string().contains("version"), "{error}");
+
+    let mut other = file.clone();
+    other[0] = b'X';
+    assert!(Snapshot::read(&mut Cursor::new(&other)).is_err());
+
+    let truncated = &file[..file.len() - 1];
+    assert!(Snapshot::read(&mut Cursor::new(truncated)).is_err());
+}
+
+#[test]
+fn snapshots_of_version_1_still_load() {
+    let (mut state, memory) = machine();
+    state.total_cycles = 1234;
+    // older versions count the clock from the cycles
+    state.half_cycles = 2468 - state.clock2 as u64;
+    let mut file = Vec::new();
+    Snapshot::new(&state, &memory).write(&mut file).unwrap();
+
+    // version 1 stored the cycle counter in 32 bits, after the registers, and had no clock
+    let clock = file.len() - 0x10000 - 8;
+    let mut old = file[..8].to_vec();
+    old.extend(1u16.to_le_bytes());
+    old.extend(&file[10..17]);
+    old.extend(1234i32.to_le_bytes());
+    old.extend(&file[25..clock]);
+    old.extend(&file[clock + 8..]);
+    let snapshot = Snapshot::read(&mut Cursor::new(&old)).unwrap();
+    assert_eq!(format!("{:?}", snapshot.state), format!("{state:?}"));
+
+    // the counter was signed
+    old[17..21].copy_from_slice(&(-1i32).to_le_bytes());
+    let error = Snapshot::read(&mut Cursor::new(&old)).unwrap_err();
+    assert!(error.to_string().contains("cycle count"), "{error}");
+}
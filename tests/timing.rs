@@ -0,0 +1,569 @@
+// Bus cycles of single instructions, every read and write in order.
+//
+// The programs run from $0600 right after the reset sequence, the expected cycles are the
+// ones listed for the NMOS 6502 in the MOS hardware manual (appendix A).
+
+mod common;
+
+use common::{Access, Recorder, R, W};
+use rs6502::fast;
+use rs6502::instruct::Variant;
+use rs6502::m6502::{self, State};
+
+// memory with the program at $0600 and the given bytes, a processor that is done resetting
+fn machine(program: &[u8], data: &[(u16, u8)]) -> (State, Recorder) {
+    let mut bus = Recorder::load(program);
+    for (addr, value) in data {
+        bus.ram[*addr as usize] = *value;
+    }
+    let mut state = State::new();
+    m6502::run_instruction(&mut state, &mut bus);
+    bus.accesses.clear();
+    (state, bus)
+}
+
+// runs one instruction, returns the cycles it took and its bus cycles
+fn run(state: &mut State, bus: &mut Recorder) -> (u32, Vec<Access>) {
+    let cycles = m6502::run_instruction(state, bus);
+    (cycles, std::mem::take(&mut bus.accesses))
+}
+
+// the instruction is the first one of the program, with X and Y set beforehand
+fn accesses(program: &[u8], x: u8, y: u8, data: &[(u16, u8)]) -> Vec<Access> {
+    let (mut state, mut bus) = machine(program, data);
+    state.registers.xr = x;
+    state.registers.yr = y;
+    let (cycles, accesses) = run(&mut state, &mut bus);
+    assert_eq!(cycles as usize, accesses.len(), "one bus access per cycle");
+    accesses
+}
+
+#[test]
+fn immediate_implied_and_accumulator() {
+    assert_eq!(
+        accesses(&[0xA9, 0x42], 0, 0, &[]),
+        [(0x0600, 0xA9, R), (0x0601, 0x42, R)]
+    );
+    // the byte after the opcode is read and thrown away
+    assert_eq!(
+        accesses(&[0xE8, 0x77], 0, 0, &[]),
+        [(0x0600, 0xE8, R), (0x0601, 0x77, R)]
+    );
+    assert_eq!(
+        accesses(&[0x0A, 0x77], 0, 0, &[]),
+        [(0x0600, 0x0A, R), (0x0601, 0x77, R)]
+    );
+}
+
+#[test]
+fn zero_page_modes() {
+    let data = [(0x0010, 0x11), (0x0015, 0x55)];
+    assert_eq!(
+        accesses(&[0xA5, 0x10], 0, 0, &data),
+        [(0x0600, 0xA5, R), (0x0601, 0x10, R), (0x0010, 0x11, R)]
+    );
+    // the base address is read while the index is added
+    assert_eq!(
+        accesses(&[0xB5, 0x10], 5, 0, &data),
+        [
+            (0x0600, 0xB5, R),
+            (0x0601, 0x10, R),
+            (0x0010, 0x11, R),
+            (0x0015, 0x55, R),
+        ]
+    );
+    assert_eq!(
+        accesses(&[0xB6, 0x10], 0, 5, &data),
+        [
+            (0x0600, 0xB6, R),
+            (0x0601, 0x10, R),
+            (0x0010, 0x11, R),
+            (0x0015, 0x55, R),
+        ]
+    );
+    assert_eq!(
+        accesses(&[0x85, 0x20], 0, 0, &[]),
+        [(0x0600, 0x85, R), (0x0601, 0x20, R), (0x0020, 0x00, W)]
+    );
+}
+
+#[test]
+fn absolute_modes() {
+    let data = [(0x1234, 0x34), (0x1239, 0x39)];
+    assert_eq!(
+        accesses(&[0xAD, 0x34, 0x12], 0, 0, &data),
+        [
+            (0x0600, 0xAD, R),
+            (0x0601, 0x34, R),
+            (0x0602, 0x12, R),
+            (0x1234, 0x34, R),
+        ]
+    );
+    assert_eq!(
+        accesses(&[0xBD, 0x34, 0x12], 5, 0, &data),
+        [
+            (0x0600, 0xBD, R),
+            (0x0601, 0x34, R),
+            (0x0602, 0x12, R),
+            (0x1239, 0x39, R),
+        ]
+    );
+    assert_eq!(
+        accesses(&[0xB9, 0x34, 0x12], 0, 5, &data),
+        [
+            (0x0600, 0xB9, R),
+            (0x0601, 0x34, R),
+            (0x0602, 0x12, R),
+            (0x1239, 0x39, R),
+        ]
+    );
+    // stores always take the cycle for the high byte, reading the address first
+    assert_eq!(
+        accesses(&[0x9D, 0x34, 0x12], 5, 0, &data),
+        [
+            (0x0600, 0x9D, R),
+            (0x0601, 0x34, R),
+            (0x0602, 0x12, R),
+            (0x1239, 0x39, R),
+            (0x1239, 0x00, W),
+        ]
+    );
+}
+
+#[test]
+fn indirect_modes() {
+    let data = [
+        (0x0024, 0x00),
+        (0x0025, 0x30),
+        (0x0030, 0x00),
+        (0x0031, 0x40),
+        (0x3000, 0x77),
+        (0x4004, 0x88),
+    ];
+    assert_eq!(
+        accesses(&[0xA1, 0x20], 4, 0, &data),
+        [
+            (0x0600, 0xA1, R),
+            (0x0601, 0x20, R),
+            (0x0020, 0x00, R),
+            (0x0024, 0x00, R),
+            (0x0025, 0x30, R),
+            (0x3000, 0x77, R),
+        ]
+    );
+    assert_eq!(
+        accesses(&[0xB1, 0x30], 0, 4, &data),
+        [
+            (0x0600, 0xB1, R),
+            (0x0601, 0x30, R),
+            (0x0030, 0x00, R),
+            (0x0031, 0x40, R),
+            (0x4004, 0x88, R),
+        ]
+    );
+    assert_eq!(
+        accesses(&[0x91, 0x30], 0, 4, &data),
+        [
+            (0x0600, 0x91, R),
+            (0x0601, 0x30, R),
+            (0x0030, 0x00, R),
+            (0x0031, 0x40, R),
+            (0x4004, 0x88, R),
+            (0x4004, 0x00, W),
+        ]
+    );
+}
+
+#[test]
+fn jumps() {
+    let (mut state, mut bus) = machine(&[0x4C, 0x34, 0x12], &[]);
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            3,
+            vec![(0x0600, 0x4C, R), (0x0601, 0x34, R), (0x0602, 0x12, R)]
+        )
+    );
+    assert_eq!(state.registers.pc, 0x1234);
+
+    let data = [(0x1234, 0x00), (0x1235, 0x20)];
+    let (mut state, mut bus) = machine(&[0x6C, 0x34, 0x12], &data);
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            5,
+            vec![
+                (0x0600, 0x6C, R),
+                (0x0601, 0x34, R),
+                (0x0602, 0x12, R),
+                (0x1234, 0x00, R),
+                (0x1235, 0x20, R),
+            ]
+        )
+    );
+    assert_eq!(state.registers.pc, 0x2000);
+}
+
+#[test]
+fn read_modify_write() {
+    // the unmodified value is written back while the new one is computed
+    assert_eq!(
+        accesses(&[0xE6, 0x10], 0, 0, &[(0x0010, 0x41)]),
+        [
+            (0x0600, 0xE6, R),
+            (0x0601, 0x10, R),
+            (0x0010, 0x41, R),
+            (0x0010, 0x41, W),
+            (0x0010, 0x42, W),
+        ]
+    );
+    assert_eq!(
+        accesses(&[0x1E, 0x34, 0x12], 5, 0, &[(0x1239, 0x81)]),
+        [
+            (0x0600, 0x1E, R),
+            (0x0601, 0x34, R),
+            (0x0602, 0x12, R),
+            (0x1239, 0x81, R),
+            (0x1239, 0x81, R),
+            (0x1239, 0x81, W),
+            (0x1239, 0x02, W),
+        ]
+    );
+}
+
+#[test]
+fn branch_not_taken() {
+    // bne with Z set falls through
+    let (mut state, mut bus) = machine(&[0xD0, 0x10], &[]);
+    state.registers.sr = 0b0010_0110;
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (2, vec![(0x0600, 0xD0, R), (0x0601, 0x10, R)])
+    );
+    assert_eq!(state.registers.pc, 0x0602);
+}
+
+#[test]
+fn reset_sequence() {
+    let mut bus = Recorder::new();
+    bus.ram[0xFFFC] = 0x34;
+    bus.ram[0xFFFD] = 0x12;
+    let mut state = State::new();
+    state.registers.sp = 0x00;
+    // the pushes are reads, the stack pointer still moves down by three
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            7,
+            vec![
+                (0x0000, 0x00, R),
+                (0x0000, 0x00, R),
+                (0x0100, 0x00, R),
+                (0x01FF, 0x00, R),
+                (0x01FE, 0x00, R),
+                (0xFFFC, 0x34, R),
+                (0xFFFD, 0x12, R),
+            ]
+        )
+    );
+    assert_eq!(state.registers.pc, 0x1234);
+    assert_eq!(state.registers.sp, 0xFD);
+}
+
+// the sequence after a nop, the opcode fetched after it is thrown away
+fn interrupt(vector: u16, brk: bool, setup: fn(&mut State)) -> (State, Vec<Access>) {
+    let program: &[u8] = if brk { &[0x00, 0xEA] } else { &[0xEA, 0xEA] };
+    let (mut state, mut bus) = machine(program, &[(vector, 0x00), (vector + 1, 0x30)]);
+    state.registers.sp = 0xFF;
+    state.registers.sr = 0x20;
+    setup(&mut state);
+    if !brk {
+        run(&mut state, &mut bus);
+    }
+    let (cycles, accesses) = run(&mut state, &mut bus);
+    assert_eq!(cycles, 7);
+    assert_eq!(state.registers.pc, 0x3000);
+    (state, accesses)
+}
+
+#[test]
+fn interrupt_sequences() {
+    let (state, irq) = interrupt(0xFFFE, false, |state| state.irq = true);
+    assert_eq!(
+        irq,
+        [
+            (0x0601, 0xEA, R),
+            (0x0601, 0xEA, R),
+            (0x01FF, 0x06, W),
+            (0x01FE, 0x01, W),
+            (0x01FD, 0x20, W),
+            (0xFFFE, 0x00, R),
+            (0xFFFF, 0x30, R),
+        ]
+    );
+    assert_eq!(state.registers.sr & 0x04, 0x04, "I is set");
+
+    // NMI ignores the I flag
+    let (_, nmi) = interrupt(0xFFFA, false, |state| {
+        state.registers.sr = 0x24;
+        state.nmi = true;
+    });
+    assert_eq!(
+        nmi[2..5],
+        [(0x01FF, 0x06, W), (0x01FE, 0x01, W), (0x01FD, 0x24, W)]
+    );
+    assert_eq!(nmi[5..], [(0xFFFA, 0x00, R), (0xFFFB, 0x30, R)]);
+
+    // BRK skips the byte after it and pushes B set
+    let (_, brk) = interrupt(0xFFFE, true, |_| {});
+    assert_eq!(
+        brk,
+        [
+            (0x0600, 0x00, R),
+            (0x0601, 0xEA, R),
+            (0x01FF, 0x06, W),
+            (0x01FE, 0x02, W),
+            (0x01FD, 0x30, W),
+            (0xFFFE, 0x00, R),
+            (0xFFFF, 0x30, R),
+        ]
+    );
+}
+
+#[test]
+fn subroutine_calls() {
+    let (mut state, mut bus) = machine(&[0x20, 0x00, 0x30], &[(0x3000, 0x60)]);
+    state.registers.sp = 0xFF;
+    // the high byte of the target is read after the return address is pushed
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            6,
+            vec![
+                (0x0600, 0x20, R),
+                (0x0601, 0x00, R),
+                (0x01FF, 0x00, R),
+                (0x01FF, 0x06, W),
+                (0x01FE, 0x02, W),
+                (0x0602, 0x30, R),
+            ]
+        )
+    );
+    assert_eq!((state.registers.pc, state.registers.sp), (0x3000, 0xFD));
+
+    // the pulled address is read once more and then incremented
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            6,
+            vec![
+                (0x3000, 0x60, R),
+                (0x3001, 0x00, R),
+                (0x01FD, 0x00, R),
+                (0x01FE, 0x02, R),
+                (0x01FF, 0x06, R),
+                (0x0602, 0x30, R),
+            ]
+        )
+    );
+    assert_eq!((state.registers.pc, state.registers.sp), (0x0603, 0xFF));
+}
+
+#[test]
+fn return_from_interrupt() {
+    let data = [(0x01FE, 0xC3), (0x01FF, 0x00), (0x0100, 0x30)];
+    let (mut state, mut bus) = machine(&[0x40], &data);
+    state.registers.sp = 0xFD;
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            6,
+            vec![
+                (0x0600, 0x40, R),
+                (0x0601, 0x00, R),
+                (0x01FD, 0x00, R),
+                (0x01FE, 0xC3, R),
+                (0x01FF, 0x00, R),
+                (0x0100, 0x30, R),
+            ]
+        )
+    );
+    assert_eq!(state.registers.pc, 0x3000);
+    assert_eq!(state.registers.sp, 0x00);
+    // B and bit 5 only exist on the stack
+    assert_eq!(state.registers.sr, 0xC3);
+}
+
+#[test]
+fn pushes_and_pulls() {
+    let program = [
+        0x48, // pha
+        0x08, // php
+        0x68, // pla
+        0x28, // plp
+    ];
+    let (mut state, mut bus) = machine(&program, &[]);
+    state.registers.sp = 0xFF;
+    state.registers.ac = 0x80;
+    state.registers.sr = 0x21;
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            3,
+            vec![(0x0600, 0x48, R), (0x0601, 0x08, R), (0x01FF, 0x80, W)]
+        )
+    );
+    // the pushed status has B set
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            3,
+            vec![(0x0601, 0x08, R), (0x0602, 0x68, R), (0x01FE, 0x31, W)]
+        )
+    );
+    // the stack pointer is read before it is incremented
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            4,
+            vec![
+                (0x0602, 0x68, R),
+                (0x0603, 0x28, R),
+                (0x01FD, 0x00, R),
+                (0x01FE, 0x31, R),
+            ]
+        )
+    );
+    assert_eq!(state.registers.ac, 0x31);
+    assert_eq!(state.registers.sr, 0x21);
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            4,
+            vec![
+                (0x0603, 0x28, R),
+                (0x0604, 0x00, R),
+                (0x01FE, 0x31, R),
+                (0x01FF, 0x80, R),
+            ]
+        )
+    );
+    assert_eq!(state.registers.sr, 0x80);
+    assert_eq!(state.registers.sp, 0xFF);
+}
+
+#[test]
+fn page_crossings() {
+    // the address with the unfixed high byte is read first
+    let data = [(0x1210, 0x10), (0x1310, 0x13)];
+    assert_eq!(
+        accesses(&[0xBD, 0xF0, 0x12], 0x20, 0, &data),
+        [
+            (0x0600, 0xBD, R),
+            (0x0601, 0xF0, R),
+            (0x0602, 0x12, R),
+            (0x1210, 0x10, R),
+            (0x1310, 0x13, R),
+        ]
+    );
+    let data = [
+        (0x0030, 0xF0),
+        (0x0031, 0x40),
+        (0x4010, 0x40),
+        (0x4110, 0x41),
+    ];
+    assert_eq!(
+        accesses(&[0xB1, 0x30], 0, 0x20, &data),
+        [
+            (0x0600, 0xB1, R),
+            (0x0601, 0x30, R),
+            (0x0030, 0xF0, R),
+            (0x0031, 0x40, R),
+            (0x4010, 0x40, R),
+            (0x4110, 0x41, R),
+        ]
+    );
+    // read-modify-write and stores take the same time either way
+    let data = [(0x1210, 0x10), (0x1310, 0x13)];
+    assert_eq!(accesses(&[0x9D, 0xF0, 0x12], 0x20, 0, &data).len(), 5);
+    assert_eq!(accesses(&[0xFE, 0xF0, 0x12], 0x20, 0, &data).len(), 7);
+}
+
+#[test]
+fn branches_taken() {
+    // the next opcode is read while the target is computed
+    let (mut state, mut bus) = machine(&[0xD0, 0x10], &[]);
+    state.registers.sr = 0x20;
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            3,
+            vec![(0x0600, 0xD0, R), (0x0601, 0x10, R), (0x0602, 0x00, R)]
+        )
+    );
+    assert_eq!(state.registers.pc, 0x0612);
+
+    // into the previous page, the target with the old high byte is read too
+    let (mut state, mut bus) = machine(&[0xD0, 0xF0], &[]);
+    state.registers.sr = 0x20;
+    assert_eq!(
+        run(&mut state, &mut bus),
+        (
+            4,
+            vec![
+                (0x0600, 0xD0, R),
+                (0x0601, 0xF0, R),
+                (0x0602, 0x00, R),
+                (0x06F2, 0x00, R),
+            ]
+        )
+    );
+    assert_eq!(state.registers.pc, 0x05F2);
+}
+
+// the 65C02 parts that take longer than the table in the manual suggests
+#[test]
+fn cmos_timing() {
+    let cmos = |program: &[u8], sr: u8| {
+        let (mut state, mut bus) = machine(program, &[(0x0010, 0x05)]);
+        state.variant = Variant::Cmos65C02;
+        state.registers.sr = sr;
+        let mut fast_state = state.clone();
+        let (cycles, accesses) = run(&mut state, &mut bus);
+        assert_eq!(cycles as usize, accesses.len(), "one bus access per cycle");
+        assert_eq!(fast::run_instruction(&mut fast_state, &mut bus), cycles);
+        accesses
+    };
+    // 5C reads from $FFxx and then from $FFFF four times
+    assert_eq!(
+        cmos(&[0x5C, 0x34, 0x12], 0x20),
+        [
+            (0x0600, 0x5C, R),
+            (0x0601, 0x34, R),
+            (0x0602, 0x12, R),
+            (0xFF34, 0x00, R),
+            (0xFFFF, 0x00, R),
+            (0xFFFF, 0x00, R),
+            (0xFFFF, 0x00, R),
+            (0xFFFF, 0x00, R),
+        ]
+    );
+    assert_eq!(
+        cmos(&[0x69, 0x01], 0x20),
+        [(0x0600, 0x69, R), (0x0601, 0x01, R)]
+    );
+    // decimal mode takes another cycle, reading the next opcode
+    assert_eq!(
+        cmos(&[0x69, 0x01], 0x28),
+        [(0x0600, 0x69, R), (0x0601, 0x01, R), (0x0602, 0x00, R)]
+    );
+    assert_eq!(
+        cmos(&[0xE5, 0x10], 0x28),
+        [
+            (0x0600, 0xE5, R),
+            (0x0601, 0x10, R),
+            (0x0010, 0x05, R),
+            (0x0602, 0x00, R),
+        ]
+    );
+}
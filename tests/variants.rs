@@ -0,0 +1,235 @@
+mod common;
+
+use common::{load, CORES};
+use rs6502::asm::assemble_for;
+use rs6502::asm::lexer::lex;
+use rs6502::asm::parser::parse;
+use rs6502::instruct::{Quirks, Variant};
+use rs6502::m6502::State;
+use rs6502::memory::{DefaultMemory, Memory};
+
+// assembles the program at $0600 and runs it in both modes until it jumps to itself
+fn run(variant: Variant, program: &[&str]) -> Vec<(State, DefaultMemory)> {
+    let mut state = State::new();
+    state.variant = variant;
+    run_with(state, program)
+}
+
+fn run_with(state: State, program: &[&str]) -> Vec<(State, DefaultMemory)> {
+    let lines: Vec<String> = program.iter().map(|l| l.to_string()).collect();
+    let mut memory = load(&[]);
+    for (key, val) in assemble_for(lines, state.variant) {
+        memory.set(key, val);
+    }
+
+    CORES
+        .into_iter()
+        .map(|run_instruction| {
+            let mut state = state.clone();
+            let mut memory = memory.clone();
+            for _ in 0..1000 {
+                let pc = state.registers.pc;
+                run_instruction(&mut state, &mut memory);
+                if state.registers.pc == pc {
+                    break;
+                }
+            }
+            (state, memory)
+        })
+        .collect()
+}
+
+#[test]
+fn cmos_instructions() {
+    let program = [
+        "lda #$F0",
+        "sta $10",
+        "lda #$30",
+        "tsb $10",
+        "lda #$80",
+        "trb $10",
+        "ldx #$22",
+        "phx",
+        "ldy #$33",
+        "phy",
+        "plx",
+        "ply",
+        "stz $11",
+        "lda #$10",
+        "sta $20",
+        "lda #$00",
+        "sta $21",
+        "lda ($20)",
+        "bra done",
+        "brk",
+        "done:",
+        "jmp done",
+    ];
+    for (state, memory) in run(Variant::Cmos65C02, &program) {
+        assert_eq!(memory.get(0x10), 0x70);
+        assert_eq!(memory.get(0x11), 0x00);
+        assert_eq!((state.registers.xr, state.registers.yr), (0x33, 0x22));
+        assert_eq!(state.registers.ac, 0x70);
+        assert_eq!(state.fault, None);
+    }
+}
+
+#[test]
+fn cmos_fixes_the_indirect_jump() {
+    let program = [
+        "lda #$00",
+        "sta $02FF",
+        "lda #$07",
+        "sta $0300",
+        "lda #$08",
+        "sta $0200",
+        "jmp ($02FF)",
+    ];
+    let mut program: Vec<&str> = program.to_vec();
+    program.extend([
+        ".ORG $0700",
+        "ldx #$07",
+        "low: jmp low",
+        ".ORG $0800",
+        "ldx #$08",
+        "high: jmp high",
+    ]);
+    for (variant, target) in [(Variant::Nmos6502, 0x08), (Variant::Cmos65C02, 0x07)] {
+        for (state, _) in run(variant, &program) {
+            assert_eq!(state.registers.xr, target, "{variant}");
+        }
+    }
+}
+
+#[test]
+fn ricoh_has_no_decimal_mode() {
+    let program = ["sed", "clc", "lda #$09", "adc #$01", "end: jmp end"];
+    for (variant, result) in [(Variant::Nmos6502, 0x10), (Variant::Ricoh2A03, 0x0A)] {
+        for (state, _) in run(variant, &program) {
+            assert_eq!(state.registers.ac, result, "{variant}");
+        }
+    }
+}
+
+#[test]
+fn ricoh_arr_has_no_decimal_mode() {
+    let program = ["sed", "clc", "lda #$55", ".byte $6B, $FF", "end: jmp end"];
+    for (variant, result) in [(Variant::Nmos6502, 0x80), (Variant::Ricoh2A03, 0x2A)] {
+        let mut state = State::new();
+        state.variant = variant;
+        state.undocumented = true;
+        for (state, _) in run_with(state, &program) {
+            assert_eq!(state.registers.ac, result, "{variant}");
+        }
+    }
+}
+
+#[test]
+fn mos6510_io_port() {
+    let program = [
+        "lda #$0F",
+        "sta $00",
+        "lda #$A5",
+        "sta $01",
+        "lda $01",
+        "ldx $00",
+        "end: jmp end",
+    ];
+    for (state, memory) in run(Variant::Mos6510, &program) {
+        // the input pins are pulled up
+        assert_eq!(state.registers.ac, 0xF5);
+        assert_eq!(state.registers.xr, 0x0F);
+        assert_eq!(state.port.output, 0xA5);
+        assert_eq!(memory.get(0x01), 0xA5);
+    }
+    for (state, _) in run(Variant::Nmos6502, &program) {
+        assert_eq!(state.registers.ac, 0xA5);
+    }
+}
+
+#[test]
+fn assembler_follows_the_variant() {
+    let assemble = |line: &str, variant| {
+        let lines = [line.to_string()];
+        lex(lines.iter())
+            .and_then(|tokens| parse(tokens, variant))
+            .map(|res| res.into_values().collect::<Vec<u8>>())
+    };
+    assert_eq!(
+        assemble("lda ($12)", Variant::Cmos65C02).unwrap(),
+        [0xB2, 0x12]
+    );
+    assert_eq!(
+        assemble("jmp ($1234,x)", Variant::Cmos65C02).unwrap(),
+        [0x7C, 0x34, 0x12]
+    );
+    assert_eq!(
+        assemble("stz $12,x", Variant::Cmos65C02).unwrap(),
+        [0x74, 0x12]
+    );
+    assert_eq!(
+        assemble("lax $12", Variant::Nmos6502).unwrap(),
+        [0xA7, 0x12]
+    );
+    assert!(assemble("bra $12", Variant::Nmos6502).is_err());
+    assert!(assemble("lax $12", Variant::Cmos65C02).is_err());
+    assert!(assemble("lda ($12)", Variant::Nmos6502).is_err());
+}
+
+#[test]
+fn addressing_quirks_follow_the_variant() {
+    let program = [
+        // JMP ($02FF) goes to $0700, or $0800 with the page wrap
+        "lda #$00",
+        "sta $02FF",
+        "lda #$07",
+        "sta $0300",
+        "lda #$08",
+        "sta $0200",
+        // the pointer at $FF is $4433, or $5533 with the wrap
+        "lda #$33",
+        "sta $FF",
+        "lda #$44",
+        "sta $0100",
+        "lda #$55",
+        "sta $00",
+        "lda #$01",
+        "sta $4433",
+        "lda #$02",
+        "sta $5533",
+        // $F0,X reads $0110, or $0010 with the wrap
+        "lda #$03",
+        "sta $0110",
+        "lda #$04",
+        "sta $10",
+        "ldy #$00",
+        "lda ($FF),Y",
+        "sta $80",
+        "ldx #$20",
+        "lda $F0,X",
+        "sta $81",
+        "jmp ($02FF)",
+        ".ORG $0700",
+        "low: jmp low",
+        ".ORG $0800",
+        "high: jmp high",
+    ];
+    let fixed = Quirks {
+        indirect_jump_wrap: false,
+        zero_page_wrap: false,
+        pointer_wrap: false,
+    };
+    for (variant, quirks, expected) in [
+        (Variant::Nmos6502, None, (0x0800, 0x02, 0x04)),
+        (Variant::Cmos65C02, None, (0x0700, 0x02, 0x04)),
+        (Variant::Nmos6502, Some(fixed), (0x0700, 0x01, 0x03)),
+    ] {
+        let mut state = State::new();
+        state.variant = variant;
+        state.quirks = quirks;
+        for (state, memory) in run_with(state, &program) {
+            let result = (state.registers.pc, memory.get(0x80), memory.get(0x81));
+            assert_eq!(result, expected, "{variant} {quirks:?}");
+        }
+    }
+}